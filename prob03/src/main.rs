@@ -1,10 +1,22 @@
+use std::env;
 use std::fs::read_to_string;
 
 fn main() {
     let input = read_to_string("inputs/input03.txt").unwrap();
     let engine = Engine::from_input(&input);
-    engine.part1();
-    engine.part2();
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(|s| s.as_str()) {
+        Some("report") => engine.print_symbol_report(),
+        Some("gears") => {
+            // gears <symbols> <neighbours> <product|sum|max>
+            let rule = GearRule::from_args(&args[1..]);
+            println!("{}", engine.gear_ratio_sum(&rule));
+        }
+        _ => {
+            engine.part1();
+            engine.part2();
+        }
+    }
 }
 
 struct Engine {
@@ -28,6 +40,77 @@ impl PartType {
     }
 }
 
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Combine {
+    Product,
+    Sum,
+    Max,
+}
+
+impl Combine {
+    fn parse(s: &str) -> Self {
+        match s {
+            "product" => Self::Product,
+            "sum" => Self::Sum,
+            "max" => Self::Max,
+            _ => panic!("unknown combine function: {}", s),
+        }
+    }
+
+    fn apply(&self, numbers: &[usize]) -> usize {
+        match self {
+            Self::Product => numbers.iter().product(),
+            Self::Sum => numbers.iter().sum(),
+            Self::Max => numbers.iter().copied().max().unwrap_or(0),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct GearRule {
+    symbols: Vec<char>,
+    neighbours: usize,
+    combine: Combine,
+}
+
+impl Default for GearRule {
+    fn default() -> Self {
+        Self {
+            symbols: vec!['*'],
+            neighbours: 2,
+            combine: Combine::Product,
+        }
+    }
+}
+
+impl GearRule {
+    fn from_args(args: &[String]) -> Self {
+        let mut rule = Self::default();
+        if let Some(symbols) = args.first() {
+            rule.symbols = symbols.chars().collect();
+        }
+        if let Some(neighbours) = args.get(1) {
+            rule.neighbours = neighbours.parse().unwrap();
+        }
+        if let Some(combine) = args.get(2) {
+            rule.combine = Combine::parse(combine);
+        }
+        rule
+    }
+
+    fn is_gear(&self, report: &SymbolReport) -> bool {
+        self.symbols.contains(&report.symbol) && report.numbers.len() == self.neighbours
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SymbolReport {
+    row: usize,
+    col: usize,
+    symbol: char,
+    numbers: Vec<usize>,
+}
+
 impl Engine {
     fn from_input(input: &str) -> Self {
         let mut schematic = vec![];
@@ -110,20 +193,38 @@ impl Engine {
     }
 
     fn part2(&self) {
-        let mut ans = 0;
-        let rows = self.schematic.len();
-        let cols = self.schematic[0].len();
-        for i in 0..rows {
-            for j in 0..cols {
-                if self.schematic[i][j] == '*' {
-                    let adj_numbers = self.find_adj_numbers(i, j);
-                    if adj_numbers.len() == 2 {
-                        ans += adj_numbers[0] * adj_numbers[1];
-                    }
+        println!("{}", self.gear_ratio_sum(&GearRule::default()));
+    }
+
+    fn gear_ratio_sum(&self, rule: &GearRule) -> usize {
+        self.symbol_report()
+            .iter()
+            .filter(|s| rule.is_gear(s))
+            .map(|s| rule.combine.apply(&s.numbers))
+            .sum()
+    }
+
+    fn symbol_report(&self) -> Vec<SymbolReport> {
+        let mut ans = vec![];
+        for (i, row) in self.schematic.iter().enumerate() {
+            for (j, &c) in row.iter().enumerate() {
+                if PartType::parse_char(c) == PartType::Symbol {
+                    ans.push(SymbolReport {
+                        row: i,
+                        col: j,
+                        symbol: c,
+                        numbers: self.find_adj_numbers(i, j),
+                    });
                 }
             }
         }
-        println!("{}", ans);
+        ans
+    }
+
+    fn print_symbol_report(&self) {
+        for s in self.symbol_report() {
+            println!("({}, {}) {} {:?}", s.row, s.col, s.symbol, s.numbers);
+        }
     }
 
     fn find_adj_numbers(&self, r: usize, c: usize) -> Vec<usize> {
@@ -185,3 +286,51 @@ impl Engine {
         num.parse().unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(symbols: &str, neighbours: usize, combine: Combine) -> GearRule {
+        GearRule {
+            symbols: symbols.chars().collect(),
+            neighbours,
+            combine,
+        }
+    }
+
+    #[test]
+    fn test_gear_rules() {
+        let engine = Engine::from_input(include_str!("../../inputs/test03.txt"));
+        assert_eq!(engine.gear_ratio_sum(&GearRule::default()), 467835);
+        assert_eq!(engine.gear_ratio_sum(&rule("*", 2, Combine::Sum)), 1855);
+        assert_eq!(engine.gear_ratio_sum(&rule("*", 2, Combine::Max)), 1222);
+        assert_eq!(engine.gear_ratio_sum(&rule("+$", 1, Combine::Sum)), 1256);
+        assert_eq!(
+            engine.gear_ratio_sum(&rule("*#", 1, Combine::Product)),
+            1250
+        );
+        assert_eq!(engine.gear_ratio_sum(&rule("*", 3, Combine::Product)), 0);
+    }
+
+    #[test]
+    fn test_symbol_report() {
+        let engine = Engine::from_input(include_str!("../../inputs/test03.txt"));
+        let report: Vec<(usize, usize, char, Vec<usize>)> = engine
+            .symbol_report()
+            .into_iter()
+            .map(|s| (s.row, s.col, s.symbol, s.numbers))
+            .collect();
+        assert_eq!(
+            report,
+            vec![
+                (1, 3, '*', vec![467, 35]),
+                (3, 6, '#', vec![633]),
+                (4, 3, '*', vec![617]),
+                (5, 5, '+', vec![592]),
+                (8, 3, '$', vec![664]),
+                (8, 5, '*', vec![755, 598]),
+            ]
+        );
+    }
+}
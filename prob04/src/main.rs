@@ -1,13 +1,32 @@
+use num_bigint::BigUint;
+use std::env;
 use std::fs::read_to_string;
 
 fn main() {
     let input = read_to_string("inputs/input04.txt").unwrap();
-    part1(&input);
-    part2(&input);
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(|s| s.as_str()) {
+        Some("score") => {
+            // score <doubling|linear|power:BASE>
+            let game = Game::from_input(&input);
+            let scoring = Scoring::parse(args.get(1).map_or("doubling", |s| s.as_str()));
+            println!("{}", game.total_score(scoring));
+        }
+        Some("cascade") => {
+            // cascade [--reach N] [--cap N]
+            let game = Game::from_input(&input);
+            let cascade = game.cascade(CascadeRules::from_args(&args[1..]));
+            cascade.print_report();
+        }
+        _ => {
+            part1(&input);
+            part2(&input);
+        }
+    }
 }
 
 fn part1(input: &str) {
-    let mut ans = 0;
+    let mut ans = BigUint::default();
     for line in input.lines() {
         let card = Card::from_line(line);
         ans += card.score();
@@ -20,6 +39,81 @@ fn part2(input: &str) {
     game.play();
 }
 
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Scoring {
+    Doubling,
+    Linear,
+    Power(u64),
+}
+
+impl Scoring {
+    fn parse(s: &str) -> Self {
+        match s {
+            "doubling" => Self::Doubling,
+            "linear" => Self::Linear,
+            _ => match s.strip_prefix("power:") {
+                Some(base) => Self::Power(base.parse().unwrap()),
+                None => panic!("unknown scoring: {}", s),
+            },
+        }
+    }
+
+    fn score(&self, win_cnt: usize) -> BigUint {
+        if win_cnt == 0 {
+            return BigUint::default();
+        }
+        let exp = u32::try_from(win_cnt - 1).unwrap();
+        match self {
+            Self::Doubling => BigUint::from(2u8).pow(exp),
+            Self::Linear => BigUint::from(win_cnt),
+            Self::Power(base) => BigUint::from(*base).pow(exp),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct CascadeRules {
+    max_reach: Option<usize>,
+    max_copies: Option<usize>,
+}
+
+impl CascadeRules {
+    fn from_args(args: &[String]) -> Self {
+        let mut rules = Self::default();
+        for w in args.windows(2) {
+            match w[0].as_str() {
+                "--reach" => rules.max_reach = Some(w[1].parse().unwrap()),
+                "--cap" => rules.max_copies = Some(w[1].parse().unwrap()),
+                _ => {}
+            }
+        }
+        rules
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Cascade {
+    copies: Vec<usize>,
+    sources: Vec<Vec<(usize, usize)>>,
+}
+
+impl Cascade {
+    fn total(&self) -> usize {
+        self.copies.iter().sum()
+    }
+
+    fn print_report(&self) {
+        for (i, cnt) in self.copies.iter().enumerate() {
+            let sources: Vec<String> = self.sources[i]
+                .iter()
+                .map(|(src, added)| format!("{}x{}", src + 1, added))
+                .collect();
+            println!("Card {}: {} [{}]", i + 1, cnt, sources.join(" "));
+        }
+        println!("total={}", self.total());
+    }
+}
+
 struct Game {
     cards: Vec<Card>,
 }
@@ -34,64 +128,164 @@ impl Game {
     }
 
     fn play(&self) {
+        let cascade = self.cascade(CascadeRules::default());
+        println!("{}", cascade.total());
+    }
+
+    fn cascade(&self, rules: CascadeRules) -> Cascade {
         let n = self.cards.len();
-        let mut card_cnt = vec![1usize; n];
-        let mut ans = 0;
+        let cap = rules.max_copies.unwrap_or(usize::MAX);
+        let mut copies = vec![1usize.min(cap); n];
+        let mut sources = vec![vec![]; n];
         for i in 0..n {
-            let cnt = card_cnt[i];
-            ans += cnt;
-            let win_cnt = self.cards[i].win_count();
-            for j in 1..win_cnt + 1 {
-                card_cnt[i + j] += cnt;
+            let cnt = copies[i];
+            let mut reach = self.cards[i].win_count();
+            if let Some(max_reach) = rules.max_reach {
+                reach = reach.min(max_reach);
+            }
+            let end = n.min(i + 1 + reach);
+            for j in i + 1..end {
+                let added = cnt.min(cap - copies[j]);
+                if added > 0 {
+                    copies[j] += added;
+                    sources[j].push((i, added));
+                }
             }
         }
-        println!("{}", ans);
+        Cascade { copies, sources }
+    }
+
+    fn total_score(&self, scoring: Scoring) -> BigUint {
+        self.cards
+            .iter()
+            .map(|c| scoring.score(c.win_count()))
+            .sum()
     }
 }
+// sorted and deduplicated, so memory follows the count of numbers, not their size
 #[derive(Debug, Clone)]
-struct Card {
-    winning_numbers: Vec<i32>,
-    numbers: Vec<i32>,
+struct NumberSet {
+    numbers: Vec<u32>,
 }
-impl Card {
-    fn from_line(line: &str) -> Self {
-        // Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
-        let parts: Vec<_> = line.split(" | ").collect();
-        let win_parts: Vec<_> = parts[0].trim().split(": ").collect();
-        let winning_numbers = Self::parse_numbers(win_parts[1]);
-        let numbers = Self::parse_numbers(parts[1]);
-        Self {
-            winning_numbers,
-            numbers,
+
+impl NumberSet {
+    fn new(mut numbers: Vec<u32>) -> Result<Self, String> {
+        numbers.sort_unstable();
+        if let Some(w) = numbers.windows(2).find(|w| w[0] == w[1]) {
+            return Err(format!("duplicate number {}", w[0]));
         }
+        Ok(Self { numbers })
     }
 
-    fn parse_numbers(s: &str) -> Vec<i32> {
-        let parts: Vec<_> = s.trim().split(' ').collect();
-        let mut ans = vec![];
-        for p in parts {
-            if p.is_empty() {
-                continue;
+    fn intersection_count(&self, other: &Self) -> usize {
+        let (a, b) = (&self.numbers, &other.numbers);
+        let (mut i, mut j, mut ans) = (0, 0, 0);
+        while i < a.len() && j < b.len() {
+            match a[i].cmp(&b[j]) {
+                std::cmp::Ordering::Less => i += 1,
+                std::cmp::Ordering::Greater => j += 1,
+                std::cmp::Ordering::Equal => {
+                    ans += 1;
+                    i += 1;
+                    j += 1;
+                }
             }
-            let n: i32 = p.trim().parse().unwrap();
-            ans.push(n);
         }
         ans
     }
+}
 
-    fn score(&self) -> i32 {
-        let cnt = self.win_count();
-        if cnt > 0 {
-            2i32.pow(cnt as u32 - 1)
-        } else {
-            0
-        }
+#[derive(Debug, Clone)]
+struct Card {
+    winning_numbers: NumberSet,
+    numbers: NumberSet,
+}
+impl Card {
+    fn from_line(line: &str) -> Self {
+        Self::parse(line).unwrap_or_else(|e| panic!("illegal card {:?}: {}", line, e))
+    }
+
+    fn parse(line: &str) -> Result<Self, String> {
+        // Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
+        let (head, numbers) = line
+            .split_once('|')
+            .ok_or_else(|| "missing '|'".to_string())?;
+        let (_, winning_numbers) = head
+            .split_once(':')
+            .ok_or_else(|| "missing ':'".to_string())?;
+        Ok(Self {
+            winning_numbers: Self::parse_numbers(winning_numbers)?,
+            numbers: Self::parse_numbers(numbers)?,
+        })
+    }
+
+    fn parse_numbers(s: &str) -> Result<NumberSet, String> {
+        let numbers = s
+            .split_whitespace()
+            .map(|p| p.parse().map_err(|_| format!("bad number {}", p)))
+            .collect::<Result<Vec<u32>, String>>()?;
+        NumberSet::new(numbers)
+    }
+
+    fn score(&self) -> BigUint {
+        Scoring::Doubling.score(self.win_count())
     }
 
     fn win_count(&self) -> usize {
-        self.numbers
-            .iter()
-            .filter(|x| self.winning_numbers.contains(x))
-            .count()
+        self.winning_numbers.intersection_count(&self.numbers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
+Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
+Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
+Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
+Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
+Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11";
+
+    #[test]
+    fn test_cascade() {
+        let game = Game::from_input(SAMPLE);
+        assert_eq!(game.total_score(Scoring::Doubling), BigUint::from(13u8));
+        let cascade = game.cascade(CascadeRules::default());
+        assert_eq!(cascade.copies, vec![1, 2, 4, 8, 14, 1]);
+        assert_eq!(cascade.total(), 30);
+        assert_eq!(cascade.sources[2], vec![(0, 1), (1, 2)]);
+    }
+
+    #[test]
+    fn test_cascade_past_last_card() {
+        let game = Game::from_input("Card 1: 1 2 | 3 4\nCard 2: 1 2 | 1 2");
+        assert_eq!(game.cascade(CascadeRules::default()).total(), 2);
+    }
+
+    #[test]
+    fn test_card_validation() {
+        assert!(Card::parse("Card 1: 1 2 2 | 3").is_err());
+        assert!(Card::parse("Card 1: 1 2 | 3 99999999999").is_err());
+        let card = Card::parse("Card 1: 1000000 7 | 1000000 3000000000").unwrap();
+        assert_eq!(card.win_count(), 1);
+        let card = Card::parse("Card 1: 1 200 5000 | 5000 3 200").unwrap();
+        assert_eq!(card.win_count(), 2);
+    }
+
+    #[test]
+    fn test_many_matches() {
+        let numbers: Vec<String> = (1..=100).map(|n| n.to_string()).collect();
+        let numbers = numbers.join(" ");
+        let line = format!("Card 1: {} | {}", numbers, numbers);
+        let card = Card::from_line(&line);
+        assert_eq!(card.win_count(), 100);
+        assert_eq!(card.score(), BigUint::from(1u8) << 99);
+        let game = Game::from_input(&line);
+        assert_eq!(game.total_score(Scoring::Linear), BigUint::from(100u8));
+        assert_eq!(
+            game.total_score(Scoring::Power(3)),
+            BigUint::from(3u8).pow(99)
+        );
     }
 }
@@ -1,117 +1,406 @@
-use std::collections::VecDeque;
+use std::collections::HashMap;
+use std::env;
 use std::fs::read_to_string;
 
 fn main() {
     let input = read_to_string("inputs/input05.txt").unwrap();
     let garden = Garden::from_input(&input);
-    garden.part1();
-    garden.part2();
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(|s| s.as_str()) {
+        Some("lookup") => {
+            // lookup <from> <to> <value>
+            let (from, to, value) = parse_query(&args[1..]);
+            println!("{:?}", garden.lookup(from, to, value));
+        }
+        Some("trace") => garden.best_range_trace().print(),
+        Some("reachable") => {
+            for r in garden.reachable_locations() {
+                println!("[{}, {})", r.start, r.start + r.range);
+            }
+        }
+        Some("preimage") => {
+            // preimage <from> <to> <value>: every <from> value mapped onto <value>
+            let (from, to, value) = parse_query(&args[1..]);
+            println!("{:?}", garden.preimage(from, to, value));
+        }
+        _ => {
+            garden.part1();
+            garden.part2();
+        }
+    }
+}
+
+fn parse_query(args: &[String]) -> (Category, Category, i64) {
+    (
+        Category::parse(&args[0]),
+        Category::parse(&args[1]),
+        args[2].parse().unwrap(),
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Category {
+    Seed,
+    Soil,
+    Fertilizer,
+    Water,
+    Light,
+    Temperature,
+    Humidity,
+    Location,
+}
+
+impl Category {
+    fn parse(s: &str) -> Self {
+        match s {
+            "seed" => Self::Seed,
+            "soil" => Self::Soil,
+            "fertilizer" => Self::Fertilizer,
+            "water" => Self::Water,
+            "light" => Self::Light,
+            "temperature" => Self::Temperature,
+            "humidity" => Self::Humidity,
+            "location" => Self::Location,
+            _ => panic!("unknown category {}", s),
+        }
+    }
 }
 
 #[derive(Debug)]
 struct Garden {
     seeds: Vec<i64>,
-    mappings: Vec<Vec<Mapping>>,
+    maps: HashMap<Category, Map>,
 }
 
 impl Garden {
     fn from_input(input: &str) -> Self {
-        let mut seeds = vec![];
-        let mut mappings = vec![];
-        let lines: Vec<_> = input.lines().collect();
-        for p in lines[0]["seeds: ".len()..].split(' ') {
-            seeds.push(p.parse().unwrap());
-        }
-        let mut i = 1;
-        while i < lines.len() {
-            if lines[i].is_empty() {
-                i += 1;
+        let mut blocks = input.split("\n\n");
+        let seeds = blocks.next().unwrap()["seeds: ".len()..]
+            .split_whitespace()
+            .map(|p| p.parse().unwrap())
+            .collect();
+        let mut maps = HashMap::new();
+        for block in blocks {
+            let lines: Vec<_> = block.lines().filter(|l| !l.is_empty()).collect();
+            if lines.is_empty() {
                 continue;
             }
-            if lines[i].contains("map") {
-                let mut new_map = vec![];
-                let mut j = i + 1;
-                while j < lines.len() && !lines[j].is_empty() {
-                    new_map.push(Mapping::from_line(lines[j]));
-                    j += 1;
+            let map = Map::from_lines(lines[0], &lines[1..]);
+            maps.insert(map.from, map);
+        }
+        Self { seeds, maps }
+    }
+
+    fn path(&self, from: Category, to: Category) -> Option<Vec<&Map>> {
+        let mut ans = vec![];
+        let mut cur = from;
+        while cur != to {
+            let map = self.maps.get(&cur)?;
+            if ans.len() > self.maps.len() {
+                return None;
+            }
+            ans.push(map);
+            cur = map.to;
+        }
+        Some(ans)
+    }
+
+    fn lookup(&self, from: Category, to: Category, value: i64) -> Option<i64> {
+        let path = self.path(from, to)?;
+        Some(path.iter().fold(value, |x, m| m.apply(x)))
+    }
+
+    fn preimage(&self, from: Category, to: Category, value: i64) -> Option<Vec<i64>> {
+        let path = self.path(from, to)?;
+        let mut cur = vec![value];
+        for m in path.iter().rev() {
+            cur = cur.iter().flat_map(|&y| m.preimage(y)).collect();
+        }
+        cur.sort();
+        cur.dedup();
+        Some(cur)
+    }
+
+    fn composed(&self, from: Category, to: Category) -> Option<Map> {
+        let path = self.path(from, to)?;
+        let identity = Map {
+            from,
+            to: from,
+            entries: vec![],
+        };
+        Some(path.into_iter().fold(identity, |acc, m| acc.compose(m)))
+    }
+
+    // a trailing seed without a length is ignored, as before
+    fn seed_ranges(&self) -> Vec<Range> {
+        self.seeds
+            .chunks_exact(2)
+            .map(|c| Range::new(c[0], c[1]))
+            .collect()
+    }
+
+    fn trace(&self, from: Category, to: Category, value: i64) -> Option<Trace> {
+        let path = self.path(from, to)?;
+        let mut steps = vec![];
+        let mut cur = value;
+        for m in path {
+            let entry = m.entry_for(cur).cloned();
+            steps.push(TraceStep {
+                category: m.from,
+                value: cur,
+                entry,
+            });
+            cur = m.apply(cur);
+        }
+        Some(Trace {
+            seed_range: None,
+            steps,
+            category: to,
+            value: cur,
+        })
+    }
+
+    fn traced_ranges(&self) -> Vec<TracedRange> {
+        let mut cur: Vec<TracedRange> = self
+            .seed_ranges()
+            .into_iter()
+            .enumerate()
+            .map(|(i, r)| TracedRange {
+                seed: r.start,
+                seed_range: i,
+                range: r,
+            })
+            .collect();
+        for m in self.path(Category::Seed, Category::Location).unwrap() {
+            let mut next = vec![];
+            for t in cur {
+                for (piece, entry) in m.split(&t.range) {
+                    next.push(TracedRange {
+                        seed: t.seed + piece.start - t.range.start,
+                        seed_range: t.seed_range,
+                        range: m.map_piece(&piece, entry),
+                    });
                 }
-                new_map.sort_by_key(|x| x.source_start);
-                mappings.push(new_map);
-                i = j;
             }
+            cur = next;
         }
-        Self { seeds, mappings }
+        cur
     }
 
-    fn part1(&self) {
-        let mut cur = self.seeds.clone();
-        for cur_map in self.mappings.iter() {
-            for x in &mut cur {
-                let source = *x;
-                for m in cur_map {
-                    if source < m.source_start {
-                        break;
-                    } else if source >= m.source_start && source < m.source_start + m.range {
-                        *x = m.target_start + source - m.source_start;
-                    }
+    fn best_range_trace(&self) -> Trace {
+        let best = self
+            .traced_ranges()
+            .into_iter()
+            .min_by_key(|t| t.range.start)
+            .unwrap();
+        let mut trace = self
+            .trace(Category::Seed, Category::Location, best.seed)
+            .unwrap();
+        trace.seed_range = Some(self.seed_ranges()[best.seed_range].clone());
+        trace
+    }
+
+    fn reachable_locations(&self) -> Vec<Range> {
+        let map = self.composed(Category::Seed, Category::Location).unwrap();
+        let mut ranges: Vec<Range> = self
+            .seed_ranges()
+            .iter()
+            .flat_map(|r| map.transform(r))
+            .collect();
+        ranges.sort_by_key(|r| r.start);
+        let mut ans: Vec<Range> = vec![];
+        for r in ranges {
+            match ans.last_mut() {
+                Some(last) if last.start + last.range >= r.start => {
+                    last.range = last.range.max(r.start + r.range - last.start);
                 }
+                _ => ans.push(r),
             }
         }
-        let ans = cur.iter().min().unwrap();
+        ans
+    }
+
+    fn part1(&self) {
+        let map = self.composed(Category::Seed, Category::Location).unwrap();
+        let ans = self.seeds.iter().map(|&s| map.apply(s)).min().unwrap();
         println!("{}", ans);
     }
 
     fn part2(&self) {
-        let mut cur_range: Vec<Range> = vec![];
-        for i in 0..self.seeds.len() / 2 {
-            cur_range.push(Range::new(self.seeds[2 * i], self.seeds[2 * i + 1]));
+        println!("{}", self.best_range_trace().value);
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TracedRange {
+    seed: i64,
+    seed_range: usize,
+    range: Range,
+}
+
+#[derive(Debug, Clone)]
+struct TraceStep {
+    category: Category,
+    value: i64,
+    entry: Option<Mapping>,
+}
+
+#[derive(Debug, Clone)]
+struct Trace {
+    seed_range: Option<Range>,
+    steps: Vec<TraceStep>,
+    category: Category,
+    value: i64,
+}
+
+impl Trace {
+    fn print(&self) {
+        if let Some(r) = &self.seed_range {
+            println!("seed range [{}, {})", r.start, r.start + r.range);
         }
-        for cur_map in self.mappings.iter() {
-            cur_range = Self::transform(cur_range, cur_map);
+        for step in &self.steps {
+            match &step.entry {
+                Some(m) => println!(
+                    "{:?} {} via {} {} {}",
+                    step.category, step.value, m.target_start, m.source_start, m.range
+                ),
+                None => println!("{:?} {} unmapped", step.category, step.value),
+            }
+        }
+        println!("{:?} {}", self.category, self.value);
+    }
+}
+
+// A piecewise map between two categories; values outside every entry map to themselves.
+#[derive(Debug, Clone)]
+struct Map {
+    from: Category,
+    to: Category,
+    // Sorted by `source_start`, non-overlapping.
+    entries: Vec<Mapping>,
+}
+
+impl Map {
+    fn from_lines(header: &str, lines: &[&str]) -> Self {
+        // seed-to-soil map:
+        let name = header.trim().trim_end_matches(" map:");
+        let (from, to) = name.split_once("-to-").unwrap();
+        let mut entries: Vec<_> = lines.iter().map(|l| Mapping::from_line(l)).collect();
+        entries.sort_by_key(|x| x.source_start);
+        Self {
+            from: Category::parse(from),
+            to: Category::parse(to),
+            entries,
+        }
+    }
+
+    fn entry_for(&self, x: i64) -> Option<&Mapping> {
+        let idx = self.entries.partition_point(|m| m.source_start <= x);
+        if idx == 0 {
+            return None;
+        }
+        let m = &self.entries[idx - 1];
+        if x < m.source_start + m.range {
+            Some(m)
+        } else {
+            None
+        }
+    }
+
+    fn apply(&self, x: i64) -> i64 {
+        match self.entry_for(x) {
+            Some(m) => m.target_start + x - m.source_start,
+            None => x,
         }
-        let ans = cur_range.iter().min_by_key(|x| x.start).unwrap().start;
-        println!("{}", ans);
     }
 
-    fn transform(mut cur_range: Vec<Range>, cur_map: &[Mapping]) -> Vec<Range> {
-        let mut new_range = vec![];
-        cur_range.sort_by_key(|r| r.start);
-        let mut q: VecDeque<Range> = VecDeque::new();
-        for r in cur_range {
-            q.push_back(r);
+    fn preimage(&self, y: i64) -> Vec<i64> {
+        let mut ans: Vec<i64> = self
+            .entries
+            .iter()
+            .filter(|m| y >= m.target_start && y < m.target_start + m.range)
+            .map(|m| m.source_start + y - m.target_start)
+            .collect();
+        if self.entry_for(y).is_none() {
+            ans.push(y);
         }
-        let mut i = 0;
-        while let Some(r) = q.pop_front() {
-            while i < cur_map.len() && cur_map[i].source_start + cur_map[i].range - 1 < r.start {
-                i += 1;
+        ans
+    }
+
+    fn split(&self, r: &Range) -> Vec<(Range, Option<usize>)> {
+        let mut ans = vec![];
+        let mut start = r.start;
+        let end = r.start + r.range;
+        for (i, m) in self.entries.iter().enumerate() {
+            let (m_start, m_end) = (m.source_start, m.source_start + m.range);
+            if m_end <= start {
+                continue;
             }
-            if i == cur_map.len() || r.start + r.range - 1 < cur_map[i].source_start {
-                new_range.push(r);
-            } else {
-                let intersect_start = r.start.max(cur_map[i].source_start);
-                if intersect_start > r.start {
-                    new_range.push(Range::new(r.start, intersect_start - r.start));
-                }
-                let intersect_end =
-                    (r.start + r.range - 1).min(cur_map[i].source_start + cur_map[i].range - 1);
-                if intersect_end < r.start + r.range - 1 {
-                    q.push_front(Range::new(
-                        intersect_end + 1,
-                        r.start + r.range - intersect_end,
-                    ));
+            if m_start >= end {
+                break;
+            }
+            if m_start > start {
+                ans.push((Range::new(start, m_start - start), None));
+                start = m_start;
+            }
+            let piece_end = m_end.min(end);
+            ans.push((Range::new(start, piece_end - start), Some(i)));
+            start = piece_end;
+        }
+        if start < end {
+            ans.push((Range::new(start, end - start), None));
+        }
+        ans
+    }
+
+    fn map_piece(&self, piece: &Range, entry: Option<usize>) -> Range {
+        match entry {
+            Some(i) => Range::new(self.entries[i].apply(piece.start), piece.range),
+            None => piece.clone(),
+        }
+    }
+
+    fn transform(&self, r: &Range) -> Vec<Range> {
+        self.split(r)
+            .iter()
+            .map(|(piece, entry)| self.map_piece(piece, *entry))
+            .collect()
+    }
+
+    fn compose(&self, next: &Map) -> Map {
+        let mut entries = vec![];
+        for m in &self.entries {
+            let image = Range::new(m.target_start, m.range);
+            for (piece, entry) in next.split(&image) {
+                entries.push(Mapping {
+                    source_start: m.source_start + piece.start - m.target_start,
+                    target_start: next.map_piece(&piece, entry).start,
+                    range: piece.range,
+                });
+            }
+        }
+        // values `self` leaves untouched are mapped by `next` alone
+        for m in &next.entries {
+            for (piece, entry) in self.split(&Range::new(m.source_start, m.range)) {
+                if entry.is_none() {
+                    entries.push(Mapping {
+                        source_start: piece.start,
+                        target_start: m.apply(piece.start),
+                        range: piece.range,
+                    });
                 }
-                let mapped_start = cur_map[i].target_start + r.start - cur_map[i].source_start;
-                new_range.push(Range::new(
-                    mapped_start,
-                    intersect_end - intersect_start + 1,
-                ));
             }
         }
-        new_range
+        entries.sort_by_key(|x| x.source_start);
+        Map {
+            from: self.from,
+            to: next.to,
+            entries,
+        }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct Range {
     start: i64,
     range: i64,
@@ -131,11 +420,75 @@ struct Mapping {
 
 impl Mapping {
     fn from_line(line: &str) -> Self {
-        let parts: Vec<_> = line.split(' ').collect();
+        let parts: Vec<_> = line.split_whitespace().collect();
         Self {
             source_start: parts[1].trim().parse().unwrap(),
             target_start: parts[0].trim().parse().unwrap(),
             range: parts[2].trim().parse().unwrap(),
         }
     }
+
+    fn apply(&self, x: i64) -> i64 {
+        self.target_start + x - self.source_start
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = include_str!("../../inputs/test05.txt");
+
+    #[test]
+    fn test_odd_seed_count() {
+        let input = SAMPLE.replacen("seeds: 79 14 55 13", "seeds: 79 14 55 13 99", 1);
+        let garden = Garden::from_input(&input);
+        assert_eq!(garden.seed_ranges().len(), 2);
+    }
+
+    #[test]
+    fn test_compose_matches_chain() {
+        let garden = Garden::from_input(SAMPLE);
+        let map = garden.composed(Category::Seed, Category::Location).unwrap();
+        for x in 0..120 {
+            assert_eq!(
+                Some(map.apply(x)),
+                garden.lookup(Category::Seed, Category::Location, x)
+            );
+        }
+        assert_eq!(
+            garden.lookup(Category::Seed, Category::Location, 13),
+            Some(35)
+        );
+    }
+
+    #[test]
+    fn test_trace() {
+        let garden = Garden::from_input(SAMPLE);
+        let trace = garden.best_range_trace();
+        assert_eq!(trace.value, 46);
+        assert_eq!(trace.steps[0].value, 82);
+        assert_eq!(trace.steps.len(), 7);
+        assert_eq!(trace.seed_range, Some(Range::new(79, 14)));
+        let reachable = garden.reachable_locations();
+        assert_eq!(reachable[0].start, 46);
+        assert!(reachable
+            .windows(2)
+            .all(|w| w[0].start + w[0].range < w[1].start));
+    }
+
+    #[test]
+    fn test_preimage() {
+        let garden = Garden::from_input(SAMPLE);
+        let seeds = garden
+            .preimage(Category::Seed, Category::Location, 35)
+            .unwrap();
+        assert!(seeds.contains(&13));
+        for s in seeds {
+            assert_eq!(
+                garden.lookup(Category::Seed, Category::Location, s),
+                Some(35)
+            );
+        }
+    }
 }
@@ -1,63 +1,251 @@
+use std::env;
+use std::fs::read_to_string;
+
 fn main() {
-    let input = "Time:        63     78     94     68
-Distance:   411   1274   2047   1035";
-    part1(input);
+    let input = read_to_string("inputs/input06.txt").unwrap();
+    // [intervals|optimal] [linear|capped:N|quadratic]
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mode = args.first().map_or("", |s| s.as_str());
+    let model_arg = match mode {
+        "intervals" | "optimal" => args.get(1),
+        _ => args.first(),
+    };
+    let charge = Charge::parse(model_arg.map_or("linear", |s| s.as_str()));
+    match mode {
+        "intervals" => {
+            for race in parse_races(&input) {
+                match race.winning_interval(charge) {
+                    Some((lo, hi)) => println!("{:?}: hold {}..={}", race, lo, hi),
+                    None => println!("{:?}: no winning hold", race),
+                }
+            }
+        }
+        "optimal" => {
+            for race in parse_races(&input) {
+                let (hold, margin) = race.optimal(charge);
+                println!("{:?}: hold {} margin {}", race, hold, margin);
+            }
+        }
+        _ => {
+            part1(&input, charge);
 
-    part2(input);
+            part2(&input, charge);
+        }
+    }
 }
 
-fn part1(input: &str) {
-    let lines: Vec<_> = input.lines().collect();
-    let times = parse_numbers(lines[0]);
-    let distances = parse_numbers(lines[1]);
-    println!("{:?} \n{:?}", times, distances);
-    let mut ans = 1i64;
-    for i in 0..times.len() {
-        let mut win_ways = 0i64;
-        let t = times[i];
-        let d = distances[i];
-        for tt in 0..=t {
-            if tt * (t - tt) > d {
-                win_ways += 1;
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Charge {
+    Linear,
+    CappedSpeed(u128),
+    Quadratic,
+}
+
+impl Charge {
+    fn parse(s: &str) -> Self {
+        match s {
+            "linear" => Self::Linear,
+            "quadratic" => Self::Quadratic,
+            _ => match s.strip_prefix("capped:") {
+                Some(cap) => Self::CappedSpeed(cap.parse().unwrap()),
+                None => panic!("unknown charge model: {}", s),
+            },
+        }
+    }
+
+    fn distance(&self, hold: u128, time: u128) -> u128 {
+        let speed = match self {
+            Self::Linear => hold,
+            Self::CappedSpeed(cap) => hold.min(*cap),
+            Self::Quadratic => hold * hold,
+        };
+        speed * (time - hold)
+    }
+
+    // A hold time reaching the maximum distance. Every model is non-decreasing
+    // before it and non-increasing after it.
+    fn best_hold(&self, time: u128) -> u128 {
+        match self {
+            Self::Linear => time / 2,
+            Self::CappedSpeed(cap) => (time / 2).min(*cap),
+            Self::Quadratic => {
+                let lo = 2 * time / 3;
+                let hi = (lo + 1).min(time);
+                if self.distance(hi, time) > self.distance(lo, time) {
+                    hi
+                } else {
+                    lo
+                }
             }
         }
-        ans *= win_ways;
     }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Race {
+    time: u128,
+    record: u128,
+}
+
+impl Race {
+    fn distance(&self, hold: u128) -> u128 {
+        hold * (self.time - hold)
+    }
+
+    fn min_winning_hold(&self) -> Option<u128> {
+        let half = self.time / 2;
+        if self.distance(half) <= self.record {
+            return None;
+        }
+        // hold * (time - hold) > record  <=>  hold > (time - sqrt(time^2 - 4 * record)) / 2
+        let disc = self.time * self.time - 4 * self.record;
+        let mut lo = (self.time - disc.isqrt()) / 2;
+        while self.distance(lo) <= self.record {
+            lo += 1;
+        }
+        while lo > 0 && self.distance(lo - 1) > self.record {
+            lo -= 1;
+        }
+        Some(lo)
+    }
+
+    fn winning_interval(&self, charge: Charge) -> Option<(u128, u128)> {
+        if charge == Charge::Linear {
+            return self.min_winning_hold().map(|lo| (lo, self.time - lo));
+        }
+        let peak = charge.best_hold(self.time);
+        let wins = |hold| charge.distance(hold, self.time) > self.record;
+        if !wins(peak) {
+            return None;
+        }
+        // first winning hold in [0, peak]
+        let (mut l, mut r) = (0, peak);
+        while l < r {
+            let mid = (l + r) / 2;
+            if wins(mid) {
+                r = mid;
+            } else {
+                l = mid + 1;
+            }
+        }
+        let lo = l;
+        // last winning hold in [peak, time]
+        let (mut l, mut r) = (peak, self.time);
+        while l < r {
+            let mid = (l + r).div_ceil(2);
+            if wins(mid) {
+                l = mid;
+            } else {
+                r = mid - 1;
+            }
+        }
+        Some((lo, l))
+    }
+
+    fn ways_to_win(&self, charge: Charge) -> u128 {
+        match self.winning_interval(charge) {
+            Some((lo, hi)) => hi - lo + 1,
+            None => 0,
+        }
+    }
+
+    fn optimal(&self, charge: Charge) -> (u128, u128) {
+        let hold = charge.best_hold(self.time);
+        let dist = charge.distance(hold, self.time);
+        (hold, dist.saturating_sub(self.record))
+    }
+}
+
+fn parse_races(input: &str) -> Vec<Race> {
+    let lines: Vec<_> = input.lines().collect();
+    let times = parse_numbers(lines[0]);
+    let distances = parse_numbers(lines[1]);
+    times
+        .iter()
+        .zip(distances.iter())
+        .map(|(&time, &record)| Race { time, record })
+        .collect()
+}
+
+fn part1(input: &str, charge: Charge) {
+    let ans: u128 = parse_races(input)
+        .iter()
+        .map(|race| race.ways_to_win(charge))
+        .product();
     println!("part1={ans}");
 }
-fn parse_numbers(line: &str) -> Vec<i64> {
+fn parse_numbers(line: &str) -> Vec<u128> {
     let mut ans = vec![];
     let parts = line.split(' ');
     for p in parts {
-        if let Ok(num) = p.parse::<i64>() {
+        if let Ok(num) = p.parse::<u128>() {
             ans.push(num);
         }
     }
     ans
 }
 
-fn parse_number2(line: &str) -> u64 {
+fn parse_number2(line: &str) -> u128 {
     let colon = line.find(':').unwrap();
     let s = &line[colon + 1..];
     let s = s.replace(' ', "");
     s.parse().unwrap()
 }
-fn part2(input: &str) {
+fn part2(input: &str, charge: Charge) {
     let lines: Vec<_> = input.lines().collect();
     let time = parse_number2(lines[0]);
-    let dist = parse_number2(lines[1]);
-    println!("{} {}", time, dist);
-    let mut l = 1u64;
-    let mut r = time / 2;
-    while l <= r {
-        let mid = (l + r) / 2;
-        let d = mid * (time - mid);
-        if d >= dist {
-            r = mid - 1;
-        } else {
-            l = mid + 1;
-        }
-    }
-    let ans = time - 2 * l + 1;
+    let record = parse_number2(lines[1]);
+    let ans = Race { time, record }.ways_to_win(charge);
     println!("part2={}", ans);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn brute_force(race: &Race, charge: Charge) -> u128 {
+        (0..=race.time)
+            .filter(|&h| charge.distance(h, race.time) > race.record)
+            .count() as u128
+    }
+
+    #[test]
+    fn test_ways_to_win() {
+        assert_eq!(Race { time: 7, record: 9 }.ways_to_win(Charge::Linear), 4);
+        let race = Race {
+            time: 30,
+            record: 200,
+        };
+        assert_eq!(race.ways_to_win(Charge::Linear), 9);
+        // a tie with the record at hold 10 and 20 does not count
+        assert_eq!(race.min_winning_hold(), Some(11));
+        for time in 0..60 {
+            for record in 0..=time * time / 4 + 1 {
+                let race = Race { time, record };
+                assert_eq!(
+                    race.ways_to_win(Charge::Linear),
+                    brute_force(&race, Charge::Linear)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_charge_models() {
+        for charge in [Charge::CappedSpeed(5), Charge::Quadratic] {
+            for time in 0..40 {
+                for record in 0..400 {
+                    let race = Race { time, record };
+                    assert_eq!(race.ways_to_win(charge), brute_force(&race, charge));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_charge() {
+        assert_eq!(Charge::parse("capped:7"), Charge::CappedSpeed(7));
+        assert_eq!(Charge::parse("quadratic"), Charge::Quadratic);
+        assert!(std::panic::catch_unwind(|| Charge::parse("quadratc")).is_err());
+    }
+}
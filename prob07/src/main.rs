@@ -1,14 +1,72 @@
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::env;
 use std::fmt::{Debug, Display, Formatter, Write};
 use std::fs::read_to_string;
 
 fn main() {
     let input = read_to_string("inputs/input07.txt").unwrap();
-    let game = Game::from_input(&input);
-    game.part1();
+    // [wild <card> | report <standard|jokers|card>] [--poker]
+    let args: Vec<String> = env::args().skip(1).collect();
+    let tie_break = if args.iter().any(|a| a == "--poker") {
+        TieBreak::GroupRank
+    } else {
+        TieBreak::CardOrder
+    };
+    match args.first().map(|s| s.as_str()) {
+        Some("wild") => {
+            let card = Card::from_char(args[1].chars().next().unwrap());
+            let game = Game::from_input(&input, Rules::Wildcard(card), tie_break);
+            println!("{}", game.total_winnings());
+        }
+        Some("report") => {
+            let rules = Rules::parse(args.get(1).map_or("standard", |s| s.as_str()));
+            let game = Game::from_input(&input, rules, tie_break);
+            print!("{}", game.report_csv());
+        }
+        _ => {
+            let game = Game::from_input(&input, Rules::Standard, tie_break);
+            println!("part1={}", game.total_winnings());
+            let game = Game::from_input(&input, Rules::JokersWild, tie_break);
+            println!("part2={}", game.total_winnings());
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Rules {
+    Standard,
+    JokersWild,
+    Wildcard(Card),
+}
+
+impl Rules {
+    fn parse(s: &str) -> Self {
+        match s {
+            "standard" => Self::Standard,
+            "jokers" => Self::JokersWild,
+            _ => Self::Wildcard(Card::from_char(s.chars().next().unwrap())),
+        }
+    }
+
+    fn wildcard(&self) -> Option<Card> {
+        match self {
+            Self::Standard => None,
+            Self::JokersWild => Some(Card::Jack),
+            Self::Wildcard(card) => Some(*card),
+        }
+    }
+
+    // Tie-break strength of a card, the wildcard ranking below everything else.
+    fn strength(&self, card: Card) -> u8 {
+        if self.wildcard() == Some(card) {
+            0
+        } else {
+            card as u8 + 1
+        }
+    }
 }
 
-#[derive(Debug, Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Copy, Clone)]
 enum HandType {
     HighCard,
     OnePair,
@@ -20,52 +78,75 @@ enum HandType {
 }
 
 impl HandType {
-    fn from_hand(cards: &[Card; 5]) -> Self {
-        let mut counter: HashMap<Card, u8> = HashMap::new();
-        // part1
-        // for c in cards.iter() {
-        //     *counter.entry(*c).or_default() += 1;
-        // }
-        // let mut values: Vec<u8> = counter.values().copied().collect();
-        // part2 start
+    fn from_signature(signature: &[u8]) -> Self {
+        let first = signature.first().copied().unwrap_or(0);
+        let second = signature.get(1).copied().unwrap_or(0);
+        match (first, second) {
+            (5.., _) => Self::FiveOfAKind,
+            (4, _) => Self::FourOfAKind,
+            (3, 2..) => Self::FullHouse,
+            (3, _) => Self::ThreeOfAKind,
+            (2, 2) => Self::TwoPair,
+            (2, _) => Self::OnePair,
+            _ => Self::HighCard,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum TieBreak {
+    CardOrder,
+    GroupRank,
+}
+
+struct Groups {
+    // (count, strength, card) sorted by count then strength, both descending.
+    groups: Vec<(u8, u8, Card)>,
+    substitution: Option<Card>,
+}
+
+impl Groups {
+    fn from_cards(cards: &[Card], rules: Rules) -> Self {
+        let mut counter = [0u8; 13];
         let mut wild_card_cnt = 0;
-        for c in cards.iter() {
-            match c {
-                Card::Jack => wild_card_cnt += 1,
-                _ => {
-                    *counter.entry(*c).or_default() += 1;
+        for &c in cards {
+            if rules.wildcard() == Some(c) {
+                wild_card_cnt += 1;
+            } else {
+                counter[c as usize] += 1;
+            }
+        }
+        let mut groups: Vec<(u8, u8, Card)> = Card::ALL
+            .iter()
+            .filter(|&&c| counter[c as usize] > 0)
+            .map(|&c| (counter[c as usize], rules.strength(c), c))
+            .collect();
+        groups.sort_by(|a, b| b.cmp(a));
+        let mut substitution = None;
+        if wild_card_cnt > 0 {
+            match groups.first_mut() {
+                Some(g) => {
+                    g.0 += wild_card_cnt;
+                    substitution = Some(g.2);
+                }
+                None => {
+                    groups.push((wild_card_cnt, 0, Card::Ace));
+                    substitution = Some(Card::Ace);
                 }
             }
         }
-        let mut values: Vec<u8> = counter.values().copied().collect();
-        values.sort();
-        if values.is_empty() {
-            return Self::FiveOfAKind;
-        }
-        let last_idx = values.len() - 1;
-        values[last_idx] += wild_card_cnt;
-        // part2 end
-
-        if values.len() == 1 {
-            Self::FiveOfAKind
-        } else if values.len() == 2 && values[1] == 4 {
-            Self::FourOfAKind
-        } else if values.len() == 2 && values[1] == 3 {
-            Self::FullHouse
-        } else if values.len() == 3 && values[2] == 3 {
-            Self::ThreeOfAKind
-        } else if values.len() == 3 && values[1] == 2 {
-            Self::TwoPair
-        } else if values.len() == 4 {
-            Self::OnePair
-        } else {
-            Self::HighCard
+        Self {
+            groups,
+            substitution,
         }
     }
+
+    fn signature(&self) -> Vec<u8> {
+        self.groups.iter().map(|g| g.0).collect()
+    }
 }
 #[derive(Ord, PartialOrd, Eq, PartialEq, Copy, Clone, Hash)]
 enum Card {
-    Jack, // comment for part1
     Two,
     Three,
     Four,
@@ -75,13 +156,29 @@ enum Card {
     Eight,
     Nine,
     Ten,
-    // Jack, // uncomment for part1
+    Jack,
     Queen,
     King,
     Ace,
 }
 
 impl Card {
+    const ALL: [Card; 13] = [
+        Self::Two,
+        Self::Three,
+        Self::Four,
+        Self::Five,
+        Self::Six,
+        Self::Seven,
+        Self::Eight,
+        Self::Nine,
+        Self::Ten,
+        Self::Jack,
+        Self::Queen,
+        Self::King,
+        Self::Ace,
+    ];
+
     fn from_char(c: char) -> Self {
         match c {
             '2' => Self::Two,
@@ -132,28 +229,65 @@ impl Display for Card {
     }
 }
 
-#[derive(Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Clone)]
 struct Hand {
     hand_type: HandType,
-    cards: [Card; 5],
+    substitution: Option<Card>,
+    // Group-count signature, a 0 separator, then the tie-break strengths.
+    key: Vec<u8>,
+    cards: Vec<Card>,
 }
 impl Hand {
-    fn from_cards(cards: &str) -> Self {
-        let chars: Vec<_> = cards.chars().collect();
-        let cards: Vec<Card> = chars[0..5].iter().map(|&x| Card::from_char(x)).collect();
-        let cards: [Card; 5] = cards.try_into().unwrap();
-        let hand_type = HandType::from_hand(&cards);
-        Self { hand_type, cards }
+    fn from_cards(cards: &str, rules: Rules, tie_break: TieBreak) -> Self {
+        let cards: Vec<Card> = cards.chars().map(Card::from_char).collect();
+        let groups = Groups::from_cards(&cards, rules);
+        let mut key = groups.signature();
+        let hand_type = HandType::from_signature(&key);
+        key.push(0);
+        match tie_break {
+            TieBreak::CardOrder => key.extend(cards.iter().map(|&c| rules.strength(c))),
+            TieBreak::GroupRank => key.extend(groups.groups.iter().map(|g| g.1)),
+        }
+        Self {
+            hand_type,
+            substitution: groups.substitution,
+            key,
+            cards,
+        }
+    }
+
+    fn compare(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+// equality agrees with the ordering: hands with the same key are equal
+impl PartialEq for Hand {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl Eq for Hand {}
+
+impl Ord for Hand {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.compare(other)
+    }
+}
+
+impl PartialOrd for Hand {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
 }
 
 impl Debug for Hand {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}{}{}{}{}",
-            self.cards[0], self.cards[1], self.cards[2], self.cards[3], self.cards[4]
-        )
+        for c in &self.cards {
+            write!(f, "{}", c)?;
+        }
+        Ok(())
     }
 }
 
@@ -169,24 +303,111 @@ struct Game {
 }
 
 impl Game {
-    fn from_input(input: &str) -> Self {
+    fn from_input(input: &str, rules: Rules, tie_break: TieBreak) -> Self {
         let mut hand_and_bids = vec![];
         for line in input.lines() {
             let parts: Vec<_> = line.split(' ').collect();
-            let hand = Hand::from_cards(parts[0]);
+            let hand = Hand::from_cards(parts[0], rules, tie_break);
             let bid: usize = parts[1].trim().parse().unwrap();
             hand_and_bids.push((hand, bid));
         }
-        hand_and_bids.sort();
+        // stable, so equal hands keep their input order
+        hand_and_bids.sort_by(|a: &(Hand, usize), b| a.0.compare(&b.0));
         Self { hand_and_bids }
     }
 
-    fn part1(&self) {
+    fn total_winnings(&self) -> usize {
         let mut ans = 0;
         for i in 0..self.hand_and_bids.len() {
             ans += (i + 1) * self.hand_and_bids[i].1
         }
-        println!("{:?}", self);
-        println!("part1={ans}");
+        ans
+    }
+
+    fn report_csv(&self) -> String {
+        let mut ans = String::from("hand,bid,type,substitution,rank,winnings\n");
+        for (i, (hand, bid)) in self.hand_and_bids.iter().enumerate() {
+            let substitution = hand.substitution.map(|c| c.to_string()).unwrap_or_default();
+            writeln!(
+                ans,
+                "{},{},{:?},{},{},{}",
+                hand,
+                bid,
+                hand.hand_type,
+                substitution,
+                i + 1,
+                (i + 1) * bid
+            )
+            .unwrap();
+        }
+        ans
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_any_size() {
+        let hand = |s| Hand::from_cards(s, Rules::Standard, TieBreak::CardOrder);
+        assert_eq!(hand("AAKKQ").hand_type, HandType::TwoPair);
+        assert_eq!(hand("AAAKKQQ").hand_type, HandType::FullHouse);
+        assert_eq!(hand("234").hand_type, HandType::HighCard);
+        assert_eq!(hand("2233").compare(&hand("AAKQ")), Ordering::Greater);
+        let wild = Hand::from_cards("JJJJJ", Rules::JokersWild, TieBreak::CardOrder);
+        assert_eq!(wild.hand_type, HandType::FiveOfAKind);
+    }
+
+    #[test]
+    fn test_rules() {
+        let input = "32T3K 765\nT55J5 684\nKK677 28\nKTJJT 220\nQQQJA 483";
+        let total = |rules| Game::from_input(input, rules, TieBreak::CardOrder).total_winnings();
+        assert_eq!(total(Rules::Standard), 6440);
+        assert_eq!(total(Rules::JokersWild), 5905);
+        assert_eq!(total(Rules::Wildcard(Card::Jack)), 5905);
+        // with tens wild, T2222 makes five of a kind but loses the tie to 22222
+        let wild = |s| Hand::from_cards(s, Rules::Wildcard(Card::Ten), TieBreak::CardOrder);
+        assert_eq!(wild("T2222").hand_type, HandType::FiveOfAKind);
+        assert!(wild("T2222") < wild("22222"));
+        assert!(wild("2T222") < wild("22222"));
+        assert_eq!(wild("JJJJT").substitution, Some(Card::Jack));
+        assert!(wild("JJJJT") > wild("QQQQ2"));
+    }
+
+    #[test]
+    fn test_tie_break() {
+        let card_order = |s| Hand::from_cards(s, Rules::Standard, TieBreak::CardOrder);
+        let group_rank = |s| Hand::from_cards(s, Rules::Standard, TieBreak::GroupRank);
+        // AQQ22 is dealt stronger, but 2AAKK holds the higher pairs
+        assert!(card_order("AQQ22") > card_order("2AAKK"));
+        assert!(group_rank("2AAKK") > group_rank("AQQ22"));
+        // threes full of twos beat twos full of threes
+        assert!(group_rank("22333") > group_rank("33222"));
+    }
+
+    #[test]
+    fn test_report() {
+        let input = "32T3K 765\nT55J5 684\nKK677 28\nKTJJT 220\nQQQJA 483";
+        let game = Game::from_input(input, Rules::JokersWild, TieBreak::CardOrder);
+        assert_eq!(game.total_winnings(), 5905);
+        let report = game.report_csv();
+        let lines: Vec<_> = report.lines().collect();
+        assert_eq!(lines[1], "32T3K,765,OnePair,,1,765");
+        assert_eq!(lines[5], "KTJJT,220,FourOfAKind,T,5,1100");
+    }
+
+    #[test]
+    fn test_duplicate_hands() {
+        // equal hands are ranked in input order
+        let input = "AAAKK 30\n23456 1\nAAAKK 10\nAAAKK 20";
+        let game = Game::from_input(input, Rules::Standard, TieBreak::CardOrder);
+        assert_eq!(game.total_winnings(), 1 + 2 * 30 + 3 * 10 + 4 * 20);
+        let group_rank = |s| Hand::from_cards(s, Rules::Standard, TieBreak::GroupRank);
+        assert_eq!(
+            group_rank("AAKKQ").cmp(&group_rank("KKAAQ")),
+            Ordering::Equal
+        );
+        assert!(group_rank("AAKKQ") == group_rank("KKAAQ"));
     }
 }
@@ -1,87 +1,357 @@
+use regex::Regex;
 use std::collections::HashMap;
+use std::env;
 use std::fs::read_to_string;
+use utils::dot::Digraph;
 
 fn main() {
     let input = read_to_string("inputs/input08.txt").unwrap();
-    let lines: Vec<_> = input.lines().collect();
-    let moves: Vec<char> = lines[0].chars().collect();
-    let mut map: HashMap<&str, (&str, &str)> = HashMap::new();
-    for line in &lines[2..] {
-        let (from, (left, right)) = parse_line(line);
-        map.insert(from, (left, right));
+    let network = Network::from_input(&input).unwrap_or_else(|e| panic!("{}", e));
+    // [<start> <end>], each `prefix:P`, `suffix:S`, `regex:R` or an exact label
+    // or: dot [highlight-start]
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(|s| s.as_str()) == Some("dot") {
+        print!("{}", network.to_dot(args.get(1).map(|s| s.as_str())));
+        return;
     }
+    if args.len() >= 2 {
+        let start = Pattern::parse(&args[0]);
+        let end = Pattern::parse(&args[1]);
+        println!("{:?}", network.solve(&start, &end));
+        return;
+    }
+    let part1 = network.solve(&Pattern::parse("AAA"), &Pattern::parse("ZZZ"));
+    println!("part1 = {:?}", part1.unwrap());
+    let part2 = network.solve(&Pattern::parse("suffix:A"), &Pattern::parse("suffix:Z"));
+    println!("part2 = {:?}", part2.unwrap());
+}
 
-    let start = "AAA";
-    println!("part1 = {:?}", part1(&moves, &map, start, |x| x == "ZZZ"));
-    part2(&moves, &map);
+#[derive(Debug, Clone)]
+enum Pattern {
+    Exact(String),
+    Prefix(String),
+    Suffix(String),
+    Regex(Regex),
 }
 
-fn part1(
-    moves: &[char],
-    map: &HashMap<&str, (&str, &str)>,
-    start: &str,
-    end: fn(&str) -> bool,
-) -> usize {
-    let mut steps = 0;
-
-    let mut cur = start;
-    loop {
-        for c in moves.iter() {
-            match c {
-                'L' => {
-                    cur = map.get(cur).unwrap().0;
-                }
-                'R' => {
-                    cur = map.get(cur).unwrap().1;
-                }
-                _ => panic!("invalid move {c}"),
-            }
-            steps += 1;
-            if end(cur) {
-                break;
+impl Pattern {
+    fn parse(s: &str) -> Self {
+        if let Some(p) = s.strip_prefix("prefix:") {
+            Self::Prefix(p.to_string())
+        } else if let Some(p) = s.strip_prefix("suffix:") {
+            Self::Suffix(p.to_string())
+        } else if let Some(p) = s.strip_prefix("regex:") {
+            Self::Regex(Regex::new(p).unwrap())
+        } else {
+            Self::Exact(s.to_string())
+        }
+    }
+
+    fn matches(&self, label: &str) -> bool {
+        match self {
+            Self::Exact(p) => label == p,
+            Self::Prefix(p) => label.starts_with(p.as_str()),
+            Self::Suffix(p) => label.ends_with(p.as_str()),
+            Self::Regex(r) => r.is_match(label),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Network {
+    // 0 for `L`, 1 for `R`.
+    moves: Vec<usize>,
+    labels: Vec<String>,
+    ids: HashMap<String, usize>,
+    next: Vec<[usize; 2]>,
+}
+
+impl Network {
+    fn from_input(input: &str) -> Result<Self, String> {
+        let mut lines = input
+            .lines()
+            .enumerate()
+            .map(|(i, l)| (i + 1, l.trim()))
+            .filter(|(_, l)| !l.is_empty());
+        let (_, first) = lines.next().ok_or("missing move line")?;
+        let moves = first
+            .chars()
+            .map(|c| match c {
+                'L' => Ok(0),
+                'R' => Ok(1),
+                _ => Err(format!("line 1: invalid move {c}")),
+            })
+            .collect::<Result<_, _>>()?;
+        let mut network = Self {
+            moves,
+            labels: vec![],
+            ids: HashMap::new(),
+            next: vec![],
+        };
+        let mut defined = vec![];
+        for (no, line) in lines {
+            let (from, (left, right)) = parse_line(line).ok_or_else(|| {
+                format!("line {}: expected `AAA = (BBB, CCC)`, got {:?}", no, line)
+            })?;
+            let from = network.intern(from);
+            let left = network.intern(left);
+            let right = network.intern(right);
+            defined.resize(network.labels.len(), false);
+            if defined[from] {
+                return Err(format!(
+                    "line {}: node {} defined twice",
+                    no, network.labels[from]
+                ));
             }
+            defined[from] = true;
+            network.next[from] = [left, right];
+        }
+        if let Some(id) = defined.iter().position(|&d| !d) {
+            return Err(format!(
+                "node {} is used but never defined",
+                network.labels[id]
+            ));
         }
-        if end(cur) {
-            break;
+        Ok(network)
+    }
+
+    fn intern(&mut self, label: &str) -> usize {
+        if let Some(&id) = self.ids.get(label) {
+            return id;
+        }
+        let id = self.labels.len();
+        self.labels.push(label.to_string());
+        self.ids.insert(label.to_string(), id);
+        self.next.push([id, id]);
+        id
+    }
+
+    fn matching(&self, pattern: &Pattern) -> Vec<usize> {
+        (0..self.labels.len())
+            .filter(|&id| pattern.matches(&self.labels[id]))
+            .collect()
+    }
+
+    fn solve(&self, start: &Pattern, end: &Pattern) -> Option<u128> {
+        let mut is_end = vec![false; self.labels.len()];
+        for id in self.matching(end) {
+            is_end[id] = true;
         }
+        let cycles: Vec<GhostCycle> = self
+            .matching(start)
+            .into_iter()
+            .map(|id| GhostCycle::analyze(self, id, &is_end))
+            .collect();
+        first_common_hit(&cycles)
+    }
+
+    fn to_dot(&self, highlight: Option<&str>) -> String {
+        let mut g = Digraph::new("network");
+        for label in &self.labels {
+            g.node(label, &[]);
+        }
+        for (id, next) in self.next.iter().enumerate() {
+            g.edge(&self.labels[id], &self.labels[next[0]], &[("label", "L")]);
+            g.edge(&self.labels[id], &self.labels[next[1]], &[("label", "R")]);
+        }
+        if let Some(start) = highlight {
+            g.highlight_reachable(start);
+        }
+        g.to_dot()
+    }
+}
+
+fn parse_line(line: &str) -> Option<(&str, (&str, &str))> {
+    // AAA = (BBB, CCC)
+    let (from, to) = line.split_once('=')?;
+    let to = to.trim().strip_prefix('(')?.strip_suffix(')')?;
+    let (left, right) = to.split_once(',')?;
+    let (from, left, right) = (from.trim(), left.trim(), right.trim());
+    if from.is_empty() || left.is_empty() || right.is_empty() {
+        return None;
     }
-    steps
+    Some((from, (left, right)))
 }
 
-fn parse_line(line: &str) -> (&str, (&str, &str)) {
-    let from = &line[..3];
-    let left = &line[7..10];
-    let right = &line[12..15];
-    (from, (left, right))
+// Where a single ghost stands on its end nodes. The walk over (node, move index)
+// states is eventually periodic: after `mu` steps it repeats every `lambda` steps.
+#[derive(Debug, Clone)]
+struct GhostCycle {
+    mu: usize,
+    lambda: usize,
+    transient_hits: Vec<usize>,
+    cycle_hits: Vec<usize>,
 }
 
-fn part2(moves: &[char], map: &HashMap<&str, (&str, &str)>) {
-    let start_nodes: Vec<&str> = map
-        .keys()
-        .filter(|x| x.chars().nth(2).unwrap() == 'A')
-        .copied()
-        .collect();
-    let mut steps_vec = vec![];
-    for node in start_nodes {
-        let steps = part1(moves, map, node, |x| x.chars().nth(2).unwrap() == 'Z');
-        steps_vec.push(steps);
-    }
-    println!("{:?}", steps_vec);
-    println!("{:?}", lcm(&steps_vec));
+impl GhostCycle {
+    fn analyze(network: &Network, start: usize, is_end: &[bool]) -> Self {
+        let n = network.moves.len();
+        // first step each (node, move index) state was reached at
+        let mut seen = vec![usize::MAX; network.labels.len() * n];
+        let mut hits = vec![];
+        let mut cur = start;
+        let mut step = 0;
+        let mu = loop {
+            let idx = step % n;
+            let state = cur * n + idx;
+            if seen[state] != usize::MAX {
+                break seen[state];
+            }
+            seen[state] = step;
+            if is_end[cur] {
+                hits.push(step);
+            }
+            cur = network.next[cur][network.moves[idx]];
+            step += 1;
+        };
+        let (transient_hits, cycle_hits) = hits.into_iter().partition(|&t| t < mu);
+        Self {
+            mu,
+            lambda: step - mu,
+            transient_hits,
+            cycle_hits,
+        }
+    }
+
+    fn hits(&self, step: usize) -> bool {
+        if step < self.mu {
+            self.transient_hits.contains(&step)
+        } else {
+            let wrapped = self.mu + (step - self.mu) % self.lambda;
+            self.cycle_hits.contains(&wrapped)
+        }
+    }
+}
+
+fn first_common_hit(cycles: &[GhostCycle]) -> Option<u128> {
+    let longest = cycles.iter().max_by_key(|c| c.mu)?;
+    // before the last ghost enters its cycle, only that ghost's transient hits can work
+    if let Some(&t) = longest
+        .transient_hits
+        .iter()
+        .find(|&&t| t >= 1 && cycles.iter().all(|c| c.hits(t)))
+    {
+        return Some(t as u128);
+    }
+    // afterwards each ghost constrains the step to one of its cycle offsets
+    let lower = longest.mu.max(1) as i128;
+    let mut candidates = vec![(0i128, 1i128)];
+    for c in cycles {
+        let mut next = vec![];
+        for &(a, m) in &candidates {
+            for &hit in &c.cycle_hits {
+                if let Some(combined) = crt((a, m), (hit as i128, c.lambda as i128)) {
+                    next.push(combined);
+                }
+            }
+        }
+        next.sort();
+        next.dedup();
+        candidates = next;
+    }
+    candidates
+        .into_iter()
+        .map(|(a, m)| {
+            if a >= lower {
+                a
+            } else {
+                a + (lower - a + m - 1) / m * m
+            }
+        })
+        .min()
+        .map(|t| t as u128)
 }
 
-pub fn lcm(nums: &[usize]) -> usize {
-    if nums.len() == 1 {
-        return nums[0];
+// Merges `x = a1 (mod m1)` and `x = a2 (mod m2)` into `x = a (mod lcm(m1, m2))`,
+// moduli need not be coprime.
+fn crt((a1, m1): (i128, i128), (a2, m2): (i128, i128)) -> Option<(i128, i128)> {
+    let (g, p, _) = ext_gcd(m1, m2);
+    let diff = a2 - a1;
+    if diff % g != 0 {
+        return None;
     }
-    let a = nums[0];
-    let b = lcm(&nums[1..]);
-    a * b / gcd_of_two_numbers(a, b)
+    let l = m1 / g * m2;
+    let k = (diff / g % (m2 / g)) * p % (m2 / g);
+    let a = (a1 + k * m1).rem_euclid(l);
+    Some((a, l))
 }
 
-fn gcd_of_two_numbers(a: usize, b: usize) -> usize {
+fn ext_gcd(a: i128, b: i128) -> (i128, i128, i128) {
     if b == 0 {
-        return a;
+        return (a, 1, 0);
+    }
+    let (g, x, y) = ext_gcd(b, a % b);
+    (g, y, x - a / b * y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cycle(mu: usize, lambda: usize, transient: &[usize], hits: &[usize]) -> GhostCycle {
+        GhostCycle {
+            mu,
+            lambda,
+            transient_hits: transient.to_vec(),
+            cycle_hits: hits.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_sample() {
+        let network = Network::from_input(include_str!("../../inputs/test08.txt")).unwrap();
+        let start = Pattern::parse("suffix:A");
+        assert_eq!(network.solve(&start, &Pattern::parse("suffix:Z")), Some(6));
+        assert_eq!(network.solve(&start, &Pattern::parse("regex:^11Z$")), None);
+        assert_eq!(network.matching(&Pattern::parse("prefix:22")).len(), 4);
+    }
+
+    #[test]
+    fn test_dot() {
+        let network = Network::from_input(include_str!("../../inputs/test08.txt")).unwrap();
+        let dot = network.to_dot(None);
+        assert!(dot.contains("\t\"11A\" -> \"11B\" [label=\"L\"]\n"));
+        assert!(dot.contains("\t\"11A\" -> \"XXX\" [label=\"R\"]\n"));
+        assert_eq!(dot.matches(" -> ").count(), 2 * 8);
+        let dot = network.to_dot(Some("22A"));
+        assert!(dot.contains("\t\"22C\" -> \"22Z\" [label=\"L\", color=\"red\"]\n"));
+        assert!(dot.contains("\t\"11A\" [color=\"gray\"]\n"));
+    }
+
+    #[test]
+    fn test_long_labels() {
+        let network = Network::from_input(
+            "LL\n\nstart =(  middle,end )\nmiddle = (end, start)\nend = (end, end)\n",
+        )
+        .unwrap();
+        let start = Pattern::parse("start");
+        assert_eq!(network.solve(&start, &Pattern::parse("end")), Some(2));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = |input| Network::from_input(input).unwrap_err();
+        assert_eq!(
+            err("LR\n\nAAA = (BBB, ZZZ)\nBBB = (AAA, AAA)"),
+            "node ZZZ is used but never defined"
+        );
+        assert!(err("LR\n\nAAA = (AAA, AAA)\nBBB (AAA, AAA)").starts_with("line 4:"));
+        assert!(err("LR\n\nAAA = (AAA, AAA)\nAAA = (AAA, AAA)").contains("defined twice"));
+        assert!(err("LX\n\nAAA = (AAA, AAA)").contains("invalid move X"));
+        assert_eq!(err(""), "missing move line");
+    }
+
+    #[test]
+    fn test_offset_cycles() {
+        // hits at 3, 8, 13, ... and 4, 11, 18, ...: the plain LCM of first hits would say 12
+        let cycles = vec![cycle(1, 5, &[], &[3]), cycle(2, 7, &[], &[4])];
+        assert_eq!(first_common_hit(&cycles), Some(18));
+        // a transient hit shared by both ghosts wins over the cycles
+        let cycles = vec![cycle(4, 5, &[2], &[6]), cycle(1, 3, &[], &[2])];
+        assert_eq!(first_common_hit(&cycles), Some(2));
+        assert_eq!(
+            first_common_hit(&[cycle(0, 4, &[], &[1]), cycle(0, 2, &[], &[0])]),
+            None
+        );
     }
-    gcd_of_two_numbers(b, a % b)
 }
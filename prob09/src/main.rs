@@ -1,23 +1,91 @@
+use std::env;
+use std::fmt::{Display, Formatter};
 use std::fs::read_to_string;
 
 fn main() {
     let input = read_to_string("inputs/input09.txt").unwrap();
-    part1_and_2(&input);
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(|s| s.as_str()) {
+        Some("fit") => {
+            for (i, line) in input.lines().enumerate() {
+                let fitted = Polynomial::fit(&parse_line(line))
+                    .and_then(|p| Ok((p.degree(), p.coefficients()?)));
+                match fitted {
+                    Ok((degree, coefficients)) => {
+                        let coefficients: Vec<String> =
+                            coefficients.iter().map(|c| c.to_string()).collect();
+                        println!("degree {}: [{}]", degree, coefficients.join(", "));
+                    }
+                    Err(e) => println!("line {}: {}", i + 1, e),
+                }
+            }
+        }
+        Some("at") => {
+            // at <k>: sum of every history extrapolated to index k
+            let k: i64 = args[1].parse().unwrap();
+            let mut ans = 0i64;
+            for (i, line) in input.lines().enumerate() {
+                match Polynomial::fit(&parse_line(line)).and_then(|p| p.value_at(k)) {
+                    Ok(value) => ans = checked_sum(ans, value, "at"),
+                    Err(e) => println!("line {}: {}, skipped", i + 1, e),
+                }
+            }
+            println!("{}", ans);
+        }
+        Some("check") => {
+            for (i, line) in input.lines().enumerate() {
+                match Polynomial::fit(&parse_line(line)) {
+                    Ok(poly) => println!("line {}: degree {}", i + 1, poly.degree()),
+                    Err(e) => println!("line {}: {}", i + 1, e),
+                }
+            }
+        }
+        _ => {
+            // [--fallback <max degree>]
+            let fallback = args
+                .iter()
+                .position(|a| a == "--fallback")
+                .map(|i| args[i + 1].parse().unwrap());
+            part1_and_2(&input, fallback);
+        }
+    }
 }
 
-fn part1_and_2(input: &str) {
+fn part1_and_2(input: &str, fallback: Option<usize>) {
     let mut ans1 = 0;
     let mut ans2 = 0;
-    for line in input.lines() {
+    for (i, line) in input.lines().enumerate() {
         let seq = parse_line(line);
-        let (a1, a2) = predict(seq);
-        ans1 += a1;
-        ans2 += a2;
+        let (a1, a2) = match (predict(&seq), fallback) {
+            (Ok(ans), _) => ans,
+            (Err(e), Some(max_degree)) if !seq.is_empty() => {
+                println!(
+                    "line {}: {}, using a degree {} best fit",
+                    i + 1,
+                    e,
+                    max_degree
+                );
+                let fit = LeastSquares::fit(&seq, max_degree);
+                let next = fit.value_at(seq.len() as i64).round() as i64;
+                (next, fit.value_at(-1).round() as i64)
+            }
+            (Err(e), _) => {
+                println!("line {}: {}, skipped", i + 1, e);
+                continue;
+            }
+        };
+        ans1 = checked_sum(ans1, a1, "part1");
+        ans2 = checked_sum(ans2, a2, "part2");
     }
     println!("part1 = {}", ans1);
     println!("part2 = {}", ans2);
 }
 
+fn checked_sum(acc: i64, value: i64, what: &str) -> i64 {
+    acc.checked_add(value)
+        .unwrap_or_else(|| panic!("{} sum overflows i64", what))
+}
+
 fn parse_line(line: &str) -> Vec<i64> {
     let mut ans = vec![];
     let parts: Vec<&str> = line.split(' ').collect();
@@ -30,25 +98,261 @@ fn parse_line(line: &str) -> Vec<i64> {
     ans
 }
 
-fn predict(seq: Vec<i64>) -> (i64, i64) {
-    let mut seqs = vec![];
-    seqs.push(seq);
-    loop {
-        let mut next_seq = vec![];
-        let last_seq = &seqs[seqs.len() - 1];
-        for i in 0..last_seq.len() - 1 {
-            next_seq.push(last_seq[i + 1] - last_seq[i]);
+fn predict(seq: &[i64]) -> Result<(i64, i64), FitError> {
+    let poly = Polynomial::fit(seq)?;
+    let next = poly.value_at(seq.len() as i64)?;
+    let prev = poly.value_at(-1)?;
+    Ok((next, prev))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FitError {
+    TooShort(usize),
+    NotPolynomial { depth: usize },
+    Overflow,
+}
+
+impl Display for FitError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TooShort(len) => write!(f, "too short ({} values)", len),
+            Self::NotPolynomial { depth } => {
+                write!(
+                    f,
+                    "not polynomial, no zero row after {} difference rows",
+                    depth
+                )
+            }
+            Self::Overflow => write!(f, "overflows i64"),
         }
-        if next_seq.iter().all(|x| *x == 0) {
-            break;
+    }
+}
+
+// An exact fraction kept in lowest terms with a positive denominator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rational {
+    num: i128,
+    den: i128,
+}
+
+impl Rational {
+    fn new(num: i128, den: i128) -> Result<Self, FitError> {
+        let g = gcd(num, den);
+        let sign = if den < 0 { -1 } else { 1 };
+        Ok(Self {
+            num: (num / g).checked_mul(sign).ok_or(FitError::Overflow)?,
+            den: (den / g).checked_mul(sign).ok_or(FitError::Overflow)?,
+        })
+    }
+
+    fn checked_add(&self, other: &Self) -> Result<Self, FitError> {
+        let num = self
+            .num
+            .checked_mul(other.den)
+            .zip(other.num.checked_mul(self.den))
+            .and_then(|(a, b)| a.checked_add(b))
+            .ok_or(FitError::Overflow)?;
+        let den = self.den.checked_mul(other.den).ok_or(FitError::Overflow)?;
+        Self::new(num, den)
+    }
+}
+
+impl Display for Rational {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.den == 1 {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
         }
-        seqs.push(next_seq);
     }
-    let mut ans1 = 0;
-    let mut ans2 = 0;
-    for sq in seqs.iter().rev() {
-        ans1 += sq[sq.len() - 1];
-        ans2 = sq[0] - ans2;
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    if b == 0 {
+        return a.abs().max(1);
+    }
+    gcd(b, a % b)
+}
+
+// A history's polynomial in Newton form: `p(k) = sum_j leading[j] * C(k, j)`,
+// where `leading[j]` is the first entry of the j-th difference row and index 0
+// is the first value of the history.
+#[derive(Debug, Clone)]
+struct Polynomial {
+    leading: Vec<i64>,
+}
+
+impl Polynomial {
+    fn fit(seq: &[i64]) -> Result<Self, FitError> {
+        if seq.len() < 2 {
+            return Err(FitError::TooShort(seq.len()));
+        }
+        let mut leading = vec![];
+        let mut row = seq.to_vec();
+        loop {
+            leading.push(row[0]);
+            let next = row
+                .windows(2)
+                .map(|w| w[1].checked_sub(w[0]).ok_or(FitError::Overflow))
+                .collect::<Result<Vec<i64>, FitError>>()?;
+            if next.is_empty() {
+                return Err(FitError::NotPolynomial {
+                    depth: leading.len() - 1,
+                });
+            }
+            if next.iter().all(|x| *x == 0) {
+                break;
+            }
+            row = next;
+        }
+        Ok(Self { leading })
+    }
+
+    fn degree(&self) -> usize {
+        self.leading.len() - 1
+    }
+
+    fn value_at(&self, k: i64) -> Result<i64, FitError> {
+        let k = k as i128;
+        let mut ans = 0i128;
+        // C(k, j), built up as C(k, j - 1) * (k - j + 1) / j, which stays integral
+        let mut binom = 1i128;
+        for (j, &d) in self.leading.iter().enumerate() {
+            if j > 0 {
+                binom = binom
+                    .checked_mul(k - j as i128 + 1)
+                    .ok_or(FitError::Overflow)?
+                    / j as i128;
+            }
+            let term = binom.checked_mul(d as i128).ok_or(FitError::Overflow)?;
+            ans = ans.checked_add(term).ok_or(FitError::Overflow)?;
+        }
+        i64::try_from(ans).map_err(|_| FitError::Overflow)
+    }
+
+    fn coefficients(&self) -> Result<Vec<Rational>, FitError> {
+        let zero = Rational::new(0, 1)?;
+        let mut ans = vec![zero; self.leading.len()];
+        // k (k - 1) ... (k - j + 1) as integer coefficients, and j!
+        let mut falling = vec![1i128];
+        let mut factorial = 1i128;
+        for (j, &d) in self.leading.iter().enumerate() {
+            if j > 0 {
+                let shift = j as i128 - 1;
+                let mut next = vec![0i128; falling.len() + 1];
+                for (i, &c) in falling.iter().enumerate() {
+                    next[i + 1] = next[i + 1].checked_add(c).ok_or(FitError::Overflow)?;
+                    let t = c.checked_mul(shift).ok_or(FitError::Overflow)?;
+                    next[i] = next[i].checked_sub(t).ok_or(FitError::Overflow)?;
+                }
+                falling = next;
+                factorial = factorial.checked_mul(j as i128).ok_or(FitError::Overflow)?;
+            }
+            for (i, &c) in falling.iter().enumerate() {
+                let num = c.checked_mul(d as i128).ok_or(FitError::Overflow)?;
+                ans[i] = ans[i].checked_add(&Rational::new(num, factorial)?)?;
+            }
+        }
+        Ok(ans)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct LeastSquares {
+    coefficients: Vec<f64>,
+}
+
+impl LeastSquares {
+    fn fit(seq: &[i64], max_degree: usize) -> Self {
+        let n = max_degree.min(seq.len() - 1) + 1;
+        // normal equations: (A^T A) c = A^T y with A[k][i] = k^i
+        let mut m = vec![vec![0f64; n + 1]; n];
+        for (k, &y) in seq.iter().enumerate() {
+            let powers: Vec<f64> = (0..n).map(|i| (k as f64).powi(i as i32)).collect();
+            for i in 0..n {
+                for j in 0..n {
+                    m[i][j] += powers[i] * powers[j];
+                }
+                m[i][n] += powers[i] * y as f64;
+            }
+        }
+        // Gauss-Jordan elimination with partial pivoting
+        for col in 0..n {
+            let pivot = (col..n)
+                .max_by(|&a, &b| m[a][col].abs().total_cmp(&m[b][col].abs()))
+                .unwrap();
+            m.swap(col, pivot);
+            let pivot_row = m[col].clone();
+            for (r, row) in m.iter_mut().enumerate() {
+                if r != col {
+                    let factor = row[col] / pivot_row[col];
+                    for (x, p) in row.iter_mut().zip(pivot_row.iter()).skip(col) {
+                        *x -= factor * p;
+                    }
+                }
+            }
+        }
+        let coefficients = (0..n).map(|i| m[i][n] / m[i][i]).collect();
+        Self { coefficients }
+    }
+
+    fn value_at(&self, k: i64) -> f64 {
+        self.coefficients
+            .iter()
+            .rev()
+            .fold(0.0, |acc, c| acc * k as f64 + c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extrapolate() {
+        assert_eq!(predict(&[0, 3, 6, 9, 12, 15]), Ok((18, -3)));
+        assert_eq!(predict(&[1, 3, 6, 10, 15, 21]), Ok((28, 0)));
+        assert_eq!(predict(&[10, 13, 16, 21, 30, 45]), Ok((68, 5)));
+        let poly = Polynomial::fit(&[1, 3, 6, 10, 15, 21]).unwrap();
+        assert_eq!(poly.degree(), 2);
+        assert_eq!(poly.value_at(-3), Ok(1));
+        assert_eq!(poly.value_at(99), Ok(5050));
+    }
+
+    #[test]
+    fn test_coefficients() {
+        // (k + 1)(k + 2) / 2
+        let poly = Polynomial::fit(&[1, 3, 6, 10, 15, 21]).unwrap();
+        let coefficients: Vec<String> = poly
+            .coefficients()
+            .unwrap()
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+        assert_eq!(coefficients, vec!["1", "3/2", "1/2"]);
+    }
+
+    #[test]
+    fn test_rejects_non_polynomial() {
+        assert_eq!(Polynomial::fit(&[7]).unwrap_err(), FitError::TooShort(1));
+        assert_eq!(
+            Polynomial::fit(&[1, 2, 4, 8, 16]).unwrap_err(),
+            FitError::NotPolynomial { depth: 4 }
+        );
+        let fit = LeastSquares::fit(&[1, 2, 4, 8, 16], 1);
+        assert_eq!(fit.coefficients.len(), 2);
+        let fit = LeastSquares::fit(&[1, 3, 5, 7], 3);
+        assert_eq!(fit.value_at(4).round(), 9.0);
+    }
+
+    #[test]
+    fn test_overflow() {
+        assert_eq!(
+            Polynomial::fit(&[i64::MIN, i64::MAX]).unwrap_err(),
+            FitError::Overflow
+        );
+        let step = i64::MAX / 4;
+        let poly = Polynomial::fit(&[0, step, 2 * step]).unwrap();
+        assert_eq!(poly.value_at(5), Err(FitError::Overflow));
     }
-    (ans1, ans2)
 }
@@ -1,175 +1,387 @@
-use std::collections::HashSet;
+use std::env;
+use std::fmt::Write;
 use std::fs::read_to_string;
+use utils::polygon::Polygon;
 
 fn main() {
     let input = read_to_string("inputs/input10.txt").unwrap();
-    let mut system = PipeSystem::from_input(&input);
-    system.part1();
-    system.part2();
+    let system = PipeSystem::from_input(&input).unwrap();
+    // [render [--no-color] | svg]
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(|s| s.as_str()) {
+        Some("render") => {
+            let color = !args.iter().any(|a| a == "--no-color");
+            print!("{}", system.render(color));
+        }
+        Some("svg") => print!("{}", system.to_svg()),
+        _ => {
+            system.part1();
+            system.part2();
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Region {
+    Loop,
+    Inside,
+    Outside,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    North,
+    South,
+    West,
+    East,
+}
+
+impl Direction {
+    const ALL: [Direction; 4] = [Self::North, Self::South, Self::West, Self::East];
+
+    fn opposite(&self) -> Self {
+        match self {
+            Self::North => Self::South,
+            Self::South => Self::North,
+            Self::West => Self::East,
+            Self::East => Self::West,
+        }
+    }
+}
+
+fn connections(tile: char) -> &'static [Direction] {
+    use Direction::*;
+    match tile {
+        '|' => &[North, South],
+        '-' => &[West, East],
+        'L' => &[North, East],
+        'J' => &[North, West],
+        '7' => &[South, West],
+        'F' => &[South, East],
+        _ => &[],
+    }
+}
+
+fn glyph(tile: char, on_loop: bool) -> char {
+    match (tile, on_loop) {
+        ('|', false) => '│',
+        ('-', false) => '─',
+        ('L', false) => '└',
+        ('J', false) => '┘',
+        ('7', false) => '┐',
+        ('F', false) => '┌',
+        ('|', true) => '┃',
+        ('-', true) => '━',
+        ('L', true) => '┗',
+        ('J', true) => '┛',
+        ('7', true) => '┓',
+        ('F', true) => '┏',
+        _ => '·',
+    }
+}
+
+fn tile_for(a: Direction, b: Direction) -> Option<char> {
+    ['|', '-', 'L', 'J', '7', 'F'].into_iter().find(|&t| {
+        let c = connections(t);
+        c.contains(&a) && c.contains(&b)
+    })
 }
 
 struct PipeSystem {
     matrix: Vec<Vec<char>>,
+    start: (usize, usize),
 }
 
 impl PipeSystem {
-    fn from_input(input: &str) -> Self {
+    fn from_input(input: &str) -> Result<Self, String> {
         let mut matrix = vec![];
         for line in input.lines() {
             let row: Vec<char> = line.chars().collect();
             matrix.push(row);
         }
-        Self { matrix }
-    }
-
-    fn find_start(&self) -> (usize, usize) {
-        let mut start = (0, 0);
-        'outer: for i in 0..self.matrix.len() {
-            for j in 0..self.matrix[0].len() {
-                if self.matrix[i][j] == 'S' {
-                    start = (i, j);
-                    break 'outer;
+        let mut system = Self {
+            matrix,
+            start: (0, 0),
+        };
+        let start = system.find_start().ok_or("no start tile")?;
+        system.start = start;
+        let dirs: Vec<Direction> = Direction::ALL
+            .into_iter()
+            .filter(|&d| {
+                system
+                    .neighbour(start, d)
+                    .is_some_and(|n| connections(system.tile(n)).contains(&d.opposite()))
+            })
+            .collect();
+        // a pipe off the loop may also point at the start, so keep the pair that closes
+        for (i, &a) in dirs.iter().enumerate() {
+            for &b in &dirs[i + 1..] {
+                system.matrix[start.0][start.1] = tile_for(a, b).unwrap();
+                if system.find_loop().is_ok() {
+                    return Ok(system);
                 }
             }
         }
-        start
-    }
-
-    fn collected_cells(&self, r: usize, c: usize) -> Vec<(usize, usize)> {
-        match self.matrix[r][c] {
-            '|' => vec![(r - 1, c), (r + 1, c)],
-            '-' => vec![(r, c - 1), (r, c + 1)],
-            'L' => vec![(r - 1, c), (r, c + 1)],
-            'J' => vec![(r - 1, c), (r, c - 1)],
-            '7' => vec![(r + 1, c), (r, c - 1)],
-            'F' => vec![(r + 1, c), (r, c + 1)],
-            '.' => vec![],
-            _ => panic!("invalid char"),
-        }
+        Err(format!(
+            "start {:?} connects to {} pipes, none of them closing a loop",
+            start,
+            dirs.len()
+        ))
     }
 
-    fn part1(&self) {
-        let start = self.find_start();
-
-        let mut cur = vec![];
-        for directions in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
-            let x = (start.0 as i32 + directions.0) as usize;
-            let y = (start.1 as i32 + directions.1) as usize;
-            let connected = self.collected_cells(x, y);
-            if connected.contains(&start) {
-                cur.push((x, y));
+    fn find_start(&self) -> Option<(usize, usize)> {
+        for (i, row) in self.matrix.iter().enumerate() {
+            if let Some(j) = row.iter().position(|&c| c == 'S') {
+                return Some((i, j));
             }
         }
+        None
+    }
 
-        let mut prev = vec![start];
-        let mut steps = 1;
+    fn tile(&self, (r, c): (usize, usize)) -> char {
+        self.matrix[r][c]
+    }
+
+    fn neighbour(&self, (r, c): (usize, usize), d: Direction) -> Option<(usize, usize)> {
+        let (r, c) = match d {
+            Direction::North => (r.checked_sub(1)?, c),
+            Direction::South => (r + 1, c),
+            Direction::West => (r, c.checked_sub(1)?),
+            Direction::East => (r, c + 1),
+        };
+        self.matrix.get(r)?.get(c)?;
+        Some((r, c))
+    }
+
+    fn find_loop(&self) -> Result<Vec<(usize, usize)>, String> {
+        let mut ans = vec![self.start];
+        let mut cur = self.start;
+        let mut dir = connections(self.tile(cur))[0];
         loop {
-            let mut next = vec![];
-            for (r, c) in &cur {
-                for connected in self.collected_cells(*r, *c) {
-                    if !prev.contains(&connected) {
-                        next.push(connected);
-                    }
-                }
+            let next = self
+                .neighbour(cur, dir)
+                .ok_or_else(|| format!("pipe at {:?} leads off the grid", cur))?;
+            let back = dir.opposite();
+            let opens = connections(self.tile(next));
+            if !opens.contains(&back) {
+                return Err(format!(
+                    "pipe at {:?} is not connected back to {:?}",
+                    next, cur
+                ));
             }
-            steps += 1;
-
-            prev = cur;
-            cur = next;
-            if cur[0] == cur[1] {
-                break;
+            if next == self.start {
+                return Ok(ans);
             }
+            ans.push(next);
+            cur = next;
+            dir = if opens[0] == back { opens[1] } else { opens[0] };
         }
-        println!("part1 = {}", steps);
     }
 
-    fn part2(&mut self) {
-        let start = self.find_start();
+    fn part1(&self) {
+        let circle = self.find_loop().unwrap();
+        println!("part1 = {}", circle.len() / 2);
+    }
 
-        let cur = self.mark_start_and_find_step_1_pipes(start);
+    fn part2(&self) {
+        println!("part2 = {}", self.enclosed_tiles());
+    }
 
-        let circle_pipes = self.find_circle(start, cur);
+    fn enclosed_tiles(&self) -> i64 {
+        let vertices = self
+            .find_loop()
+            .unwrap()
+            .into_iter()
+            .map(|(r, c)| (r as i64, c as i64))
+            .collect();
+        Polygon::new(vertices).interior_points()
+    }
 
-        let mut ans = 0;
-        let mut inside = false;
-        // scan from top to bottom and left to right, counting how many tiles are inside the loop.
-        // keep track of a boolean that tells me if I'm inside the loop
-        // every time I cross a vertical pipe that does not horizontally block the top (the place where I am in the loop), flip that state
-        for i in 0..self.matrix.len() {
-            for j in 0..self.matrix[0].len() {
-                if circle_pipes.contains(&(i, j)) {
-                    match self.matrix[i][j] {
-                        '|' | 'J' | 'L' => inside = !inside,
-                        _ => {}
+    // Classifies every tile with the scanline rule: walking a row left to right,
+    // crossing a loop pipe that opens north flips between outside and inside.
+    fn regions(&self) -> Vec<Vec<Region>> {
+        let mut ans: Vec<Vec<Region>> = self
+            .matrix
+            .iter()
+            .map(|row| vec![Region::Outside; row.len()])
+            .collect();
+        for (r, c) in self.find_loop().unwrap() {
+            ans[r][c] = Region::Loop;
+        }
+        for (i, row) in ans.iter_mut().enumerate() {
+            let mut inside = false;
+            for (j, region) in row.iter_mut().enumerate() {
+                if *region == Region::Loop {
+                    if connections(self.matrix[i][j]).contains(&Direction::North) {
+                        inside = !inside;
                     }
                 } else if inside {
-                    ans += 1;
+                    *region = Region::Inside;
                 }
             }
         }
-
-        println!("part2 = {}", ans);
+        ans
     }
 
-    fn mark_start_and_find_step_1_pipes(&mut self, start: (usize, usize)) -> Vec<(usize, usize)> {
-        let mut cur = vec![];
-        let (x, y) = start;
-        let (mut north, mut south, mut west, mut east) = (false, false, false, false);
-        if self.collected_cells(x - 1, y).contains(&start) {
-            north = true;
-            cur.push((x - 1, y));
-        }
-        if self.collected_cells(x + 1, y).contains(&start) {
-            south = true;
-            cur.push((x + 1, y));
-        }
-        if self.collected_cells(x, y - 1).contains(&start) {
-            west = true;
-            cur.push((x, y - 1));
-        }
-        if self.collected_cells(x, y + 1).contains(&start) {
-            east = true;
-            cur.push((x, y + 1));
-        }
-        match (north, south, west, east) {
-            (true, true, _, _) => self.matrix[x][y] = '|',
-            (true, _, true, _) => self.matrix[x][y] = 'J',
-            (true, _, _, true) => self.matrix[x][y] = 'L',
-            (_, true, true, _) => self.matrix[x][y] = '7',
-            (_, true, _, true) => self.matrix[x][y] = 'F',
-            (_, _, true, true) => self.matrix[x][y] = '-',
-            _ => panic!("illegal start"),
+    fn render(&self, color: bool) -> String {
+        let regions = self.regions();
+        let mut ans = String::new();
+        for (i, row) in self.matrix.iter().enumerate() {
+            for (j, &tile) in row.iter().enumerate() {
+                let region = regions[i][j];
+                let g = glyph(tile, region == Region::Loop);
+                if !color {
+                    ans.push(g);
+                    continue;
+                }
+                let style = match region {
+                    Region::Loop if (i, j) == self.start => "1;31",
+                    Region::Loop => "1;33",
+                    Region::Inside => "30;42",
+                    Region::Outside => "2",
+                };
+                write!(ans, "\x1b[{}m{}\x1b[0m", style, g).unwrap();
+            }
+            ans.push('\n');
         }
-        cur
+        ans
     }
 
-    fn find_circle(
-        &self,
-        start: (usize, usize),
-        mut cur: Vec<(usize, usize)>,
-    ) -> HashSet<(usize, usize)> {
-        let mut prev = vec![start];
-        let mut ans = HashSet::new();
-        ans.insert(start);
-        loop {
-            let mut next = vec![];
-            for (r, c) in &cur {
-                for connected in self.collected_cells(*r, *c) {
-                    if !prev.contains(&connected) {
-                        next.push(connected);
-                    }
-                }
+    fn to_svg(&self) -> String {
+        const CELL: usize = 10;
+        let regions = self.regions();
+        let height = self.matrix.len() * CELL;
+        let width = self.matrix.iter().map(|r| r.len()).max().unwrap_or(0) * CELL;
+        let mut ans = String::new();
+        writeln!(
+            ans,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">",
+            width, height
+        )
+        .unwrap();
+        for (i, row) in regions.iter().enumerate() {
+            for (j, region) in row.iter().enumerate() {
+                let fill = match region {
+                    Region::Loop => continue,
+                    Region::Inside => "#7fd37f",
+                    Region::Outside => "#eeeeee",
+                };
+                writeln!(
+                    ans,
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                    j * CELL,
+                    i * CELL,
+                    CELL,
+                    CELL,
+                    fill
+                )
+                .unwrap();
             }
-            for p in &cur {
-                ans.insert(*p);
-            }
-            prev = cur;
-            cur = next;
-            if cur[0] == cur[1] {
-                ans.insert(cur[0]);
-                break;
+        }
+        let center = |(r, c): (usize, usize)| (c * CELL + CELL / 2, r * CELL + CELL / 2);
+        // pipes off the loop as thin half-segments from each tile's centre
+        for (i, row) in self.matrix.iter().enumerate() {
+            for (j, &tile) in row.iter().enumerate() {
+                if regions[i][j] == Region::Loop {
+                    continue;
+                }
+                let (x, y) = center((i, j));
+                for d in connections(tile) {
+                    let (dx, dy): (isize, isize) = match d {
+                        Direction::North => (0, -1),
+                        Direction::South => (0, 1),
+                        Direction::West => (-1, 0),
+                        Direction::East => (1, 0),
+                    };
+                    let half = (CELL / 2) as isize;
+                    writeln!(
+                        ans,
+                        "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"#999999\"/>",
+                        x,
+                        y,
+                        x as isize + dx * half,
+                        y as isize + dy * half
+                    )
+                    .unwrap();
+                }
             }
         }
+        let points: Vec<String> = self
+            .find_loop()
+            .unwrap()
+            .into_iter()
+            .map(|p| {
+                let (x, y) = center(p);
+                format!("{},{}", x, y)
+            })
+            .collect();
+        writeln!(
+            ans,
+            "<polygon points=\"{}\" fill=\"none\" stroke=\"#d4a017\" stroke-width=\"3\"/>",
+            points.join(" ")
+        )
+        .unwrap();
+        ans.push_str("</svg>\n");
         ans
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_start_on_edge() {
+        let system = PipeSystem::from_input("S-7\n|.|\nL-J").unwrap();
+        assert_eq!(system.tile((0, 0)), 'F');
+        assert_eq!(system.find_loop().unwrap().len(), 8);
+    }
+
+    #[test]
+    fn test_enclosed() {
+        let input = "..........\n.S------7.\n.|F----7|.\n.||....||.\n.||....||.\n.|L-7F-J|.\n.|..||..|.\n.L--JL--J.\n..........";
+        let system = PipeSystem::from_input(input).unwrap();
+        assert_eq!(system.enclosed_tiles(), 4);
+    }
+
+    #[test]
+    fn test_render() {
+        let input = "..........\n.S------7.\n.|F----7|.\n.||....||.\n.||....||.\n.|L-7F-J|.\n.|..||..|.\n.L--JL--J.\n..........";
+        let system = PipeSystem::from_input(input).unwrap();
+        let inside = system
+            .regions()
+            .iter()
+            .flatten()
+            .filter(|r| **r == Region::Inside)
+            .count();
+        assert_eq!(inside, 4);
+        assert!(system.render(false).starts_with("··········\n·┏━━━━━━┓·\n"));
+        assert!(system.render(true).contains("\x1b[30;42m·\x1b[0m"));
+        let svg = system.to_svg();
+        assert!(svg.starts_with(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"100\" height=\"90\">\n"
+        ));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("fill=\"#7fd37f\"").count(), 4);
+        assert_eq!(svg.matches("<polygon ").count(), 1);
+        assert!(svg.contains("points=\"15,15 15,25 "));
+    }
+
+    #[test]
+    fn test_start_with_stray_neighbour() {
+        // the `|` above S, and then the `-` left of it, point at it but are not on the loop
+        let system = PipeSystem::from_input(".|...\n.S-7.\n.|.|.\n.L-J.").unwrap();
+        assert_eq!(system.tile((1, 1)), 'F');
+        let system = PipeSystem::from_input(".|...\n-S-7.\n.|.|.\n.L-J.").unwrap();
+        assert_eq!(system.tile((1, 1)), 'F');
+        assert_eq!(system.find_loop().unwrap().len(), 8);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(PipeSystem::from_input(".|.\n-S-\n.|.").is_err());
+        assert!(PipeSystem::from_input("S..\n...").is_err());
+        // S joins two pipes, but the loop through them is broken
+        assert!(PipeSystem::from_input("S-7\n|.|\nL-.").is_err());
+    }
+}
@@ -1,13 +1,39 @@
 use std::collections::HashSet;
+use std::env;
 use std::fs::read_to_string;
 
 fn main() {
     let input = read_to_string("inputs/input11.txt").unwrap();
-    let mut board = Board::from_input(&input);
-    board.part1();
-
     let board = Board::from_input(&input);
-    board.part2(1e6 as usize);
+    // galaxies are numbered from 1 as in the puzzle, factors default to 2
+    // distance <i> <j> [factor] | nearest <i> <k> [factor] | farthest [factor] | <factor>
+    let args: Vec<String> = env::args().skip(1).collect();
+    let factor = |i: usize| args.get(i).map_or(2, |f| f.parse().unwrap());
+    let galaxy = |i: usize| args[i].parse::<usize>().unwrap() - 1;
+    match args.first().map(|s| s.as_str()) {
+        Some("distance") => {
+            let galaxies = board.expanded_galaxies(factor(3));
+            println!("{}", distance(&galaxies, galaxy(1), galaxy(2)));
+        }
+        Some("nearest") => {
+            let galaxies = board.expanded_galaxies(factor(3));
+            let k = args[2].parse().unwrap();
+            for (j, d) in nearest(&galaxies, galaxy(1), k) {
+                println!("{} {}", j + 1, d);
+            }
+        }
+        Some("farthest") => {
+            let galaxies = board.expanded_galaxies(factor(1));
+            if let Some((i, j, d)) = farthest_pair(&galaxies) {
+                println!("{} {} {}", i + 1, j + 1, d);
+            }
+        }
+        Some(_) => println!("{}", board.total_distance(factor(0))),
+        None => {
+            board.part1();
+            board.part2(1e6 as u128);
+        }
+    }
 }
 
 struct Board {
@@ -24,69 +50,16 @@ impl Board {
         Self { matrix }
     }
 
-    fn expand(&mut self) {
-        self.expand_rows();
-        self.expand_cols();
+    fn part1(&self) {
+        println!("part1 = {}", self.total_distance(2));
     }
 
-    fn expand_rows(&mut self) {
-        let mut expanded = vec![];
-        for row in self.matrix.iter() {
-            if row.contains(&'#') {
-                expanded.push(row.clone());
-            } else {
-                expanded.push(row.clone());
-                expanded.push(row.clone());
-            }
-        }
-        self.matrix = expanded
-    }
-
-    fn expand_cols(&mut self) {
-        let m = self.matrix.len();
-        let n = self.matrix[0].len();
-        let mut expanded = vec![vec![]; m];
-        for j in 0..n {
-            let mut has_galaxy = false;
-            for i in 0..m {
-                if self.matrix[i][j] == '#' {
-                    has_galaxy = true;
-                    break;
-                }
-            }
-            for (i, e) in expanded.iter_mut().enumerate() {
-                e.push(self.matrix[i][j]);
-                if !has_galaxy {
-                    e.push(self.matrix[i][j]);
-                }
-            }
-        }
-        self.matrix = expanded
-    }
-
-    fn part1(&mut self) {
-        self.expand();
-        let galaxies = self.collect_galaxies();
-        let mut ans = 0;
-        for i in 0..galaxies.len() - 1 {
-            let (x1, y1) = galaxies[i];
-
-            for (j, (x2, y2)) in galaxies.iter().enumerate().skip(i + 1) {
-                if j == i {
-                    continue;
-                }
-                ans += x1.abs_diff(*x2) + y1.abs_diff(*y2);
-            }
-        }
-        println!("part1 = {}", ans);
-    }
-
-    fn collect_galaxies(&self) -> Vec<(i32, i32)> {
+    fn collect_galaxies(&self) -> Vec<(usize, usize)> {
         let mut ans = vec![];
         for i in 0..self.matrix.len() {
             for j in 0..self.matrix[0].len() {
                 if self.matrix[i][j] == '#' {
-                    ans.push((i as i32, j as i32));
+                    ans.push((i, j));
                 }
             }
         }
@@ -107,34 +80,121 @@ impl Board {
         (rows, cols)
     }
 
-    fn part2(&self, expand_scale: usize) {
-        let mut ans = 0;
+    fn expanded_galaxies(&self, factor: u128) -> Vec<(u128, u128)> {
         let (rows, cols) = self.rows_and_cols_need_expand();
-        let galaxies = self.collect_galaxies();
-        for i in 0..galaxies.len() - 1 {
-            let (x1, y1) = galaxies[i];
-            let (x1, y1) = (x1 as usize, y1 as usize);
-            for (j, (x2, y2)) in galaxies.iter().enumerate().skip(i + 1) {
-                if j == i {
-                    continue;
-                }
-                let (x2, y2) = (*x2 as usize, *y2 as usize);
-                for x in x1.min(x2)..x1.max(x2) {
-                    if rows.contains(&x) {
-                        ans += expand_scale;
-                    } else {
-                        ans += 1;
-                    }
-                }
-                for y in y1.min(y2)..y1.max(y2) {
-                    if cols.contains(&y) {
-                        ans += expand_scale;
-                    } else {
-                        ans += 1;
-                    }
-                }
-            }
+        let row_offsets = expanded_offsets(self.matrix.len(), &rows, factor);
+        let col_offsets = expanded_offsets(self.matrix[0].len(), &cols, factor);
+        self.collect_galaxies()
+            .into_iter()
+            .map(|(x, y)| (row_offsets[x], col_offsets[y]))
+            .collect()
+    }
+
+    fn total_distance(&self, factor: u128) -> u128 {
+        let galaxies = self.expanded_galaxies(factor);
+        let xs = galaxies.iter().map(|g| g.0).collect();
+        let ys = galaxies.iter().map(|g| g.1).collect();
+        pairwise_distance_sum(xs) + pairwise_distance_sum(ys)
+    }
+
+    fn part2(&self, expand_scale: u128) {
+        println!("part2 = {}", self.total_distance(expand_scale));
+    }
+}
+
+fn distance(galaxies: &[(u128, u128)], i: usize, j: usize) -> u128 {
+    let (a, b) = (galaxies[i], galaxies[j]);
+    a.0.abs_diff(b.0) + a.1.abs_diff(b.1)
+}
+
+fn nearest(galaxies: &[(u128, u128)], i: usize, k: usize) -> Vec<(usize, u128)> {
+    let mut ans: Vec<(usize, u128)> = (0..galaxies.len())
+        .filter(|&j| j != i)
+        .map(|j| (j, distance(galaxies, i, j)))
+        .collect();
+    ans.sort_by_key(|&(j, d)| (d, j));
+    ans.truncate(k);
+    ans
+}
+
+// The pair of galaxies furthest apart. The Manhattan distance is the larger of the
+// spreads of `x + y` and `x - y`, so one pass over the galaxies is enough.
+fn farthest_pair(galaxies: &[(u128, u128)]) -> Option<(usize, usize, u128)> {
+    if galaxies.len() < 2 {
+        return None;
+    }
+    // `x - y` is shifted by the largest `y` to stay unsigned
+    let max_y = galaxies.iter().map(|g| g.1).max().unwrap();
+    let mut best = None;
+    for flip in [false, true] {
+        let key = |g: &(u128, u128)| {
+            let y = if flip { max_y - g.1 } else { g.1 };
+            g.0.checked_add(y)
+                .expect("galaxy coordinates overflow u128")
+        };
+        let idx = 0..galaxies.len();
+        let lo = idx.clone().min_by_key(|&i| key(&galaxies[i])).unwrap();
+        let hi = idx.max_by_key(|&i| key(&galaxies[i])).unwrap();
+        let d = distance(galaxies, lo, hi);
+        if best.is_none_or(|(_, _, b)| d > b) {
+            best = Some((lo.min(hi), lo.max(hi), d));
         }
-        println!("part2 = {}", ans);
+    }
+    best
+}
+
+fn expanded_offsets(len: usize, empty: &HashSet<usize>, factor: u128) -> Vec<u128> {
+    let mut ans = Vec::with_capacity(len);
+    let mut pos = 0;
+    for i in 0..len {
+        ans.push(pos);
+        pos += if empty.contains(&i) { factor } else { 1 };
+    }
+    ans
+}
+
+// Sum of `|a - b|` over all pairs: once sorted, the k-th value is larger than the k before it.
+fn pairwise_distance_sum(mut values: Vec<u128>) -> u128 {
+    values.sort_unstable();
+    let mut ans = 0;
+    let mut prefix = 0;
+    for (k, v) in values.into_iter().enumerate() {
+        ans += v * k as u128 - prefix;
+        prefix += v;
+    }
+    ans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_total_distance() {
+        let board = Board::from_input(include_str!("../../inputs/test11.txt"));
+        assert_eq!(board.total_distance(2), 374);
+        assert_eq!(board.total_distance(10), 1030);
+        assert_eq!(board.total_distance(100), 8410);
+    }
+
+    #[test]
+    fn test_queries() {
+        let board = Board::from_input(include_str!("../../inputs/test11.txt"));
+        let galaxies = board.expanded_galaxies(2);
+        assert_eq!(distance(&galaxies, 4, 8), 9);
+        assert_eq!(distance(&galaxies, 0, 6), 15);
+        assert_eq!(nearest(&galaxies, 7, 2), vec![(8, 5), (4, 6)]);
+        let (i, j, d) = farthest_pair(&galaxies).unwrap();
+        let brute = (0..9)
+            .flat_map(|a| (0..9).map(move |b| (a, b)))
+            .map(|(a, b)| distance(&galaxies, a, b))
+            .max()
+            .unwrap();
+        assert_eq!(d, brute);
+        assert_eq!(distance(&galaxies, i, j), d);
+        // past i128::MAX the keys must not wrap
+        let big = u128::MAX / 2;
+        let far = vec![(0, big), (big, 0), (big - 1, big - 1), (1, 1)];
+        assert_eq!(farthest_pair(&far), Some((0, 1, 2 * big)));
     }
 }
@@ -1,38 +1,70 @@
+use num_bigint::{BigUint, RandBigInt};
+use rand::Rng;
+use std::env;
 use std::fs::read_to_string;
 
 fn main() {
     let input = read_to_string("inputs/input12.txt").unwrap();
-    part1(&input);
-    part2(&input);
+    // [fold] | list <record> | kth <record> <k> | random <record> [count]
+    let args: Vec<String> = env::args().skip(1).collect();
+    let record = || Record::from_line(&args[1], 1);
+    match args.first().map(|s| s.as_str()) {
+        Some("list") => {
+            for filling in record().arrangements() {
+                println!("{}", filling);
+            }
+        }
+        Some("kth") => {
+            let k = args[2].parse().unwrap();
+            match record().kth_arrangement(k) {
+                Some(filling) => println!("{}", filling),
+                None => println!("out of range"),
+            }
+        }
+        Some("random") => {
+            let count = args.get(2).map_or(1, |n| n.parse().unwrap());
+            let record = record();
+            let mut rng = rand::thread_rng();
+            for _ in 0..count {
+                match record.random_arrangement(&mut rng) {
+                    Some(filling) => println!("{}", filling),
+                    None => println!("no arrangements"),
+                }
+            }
+        }
+        Some(fold) => println!("{}", total_arrangements(&input, fold.parse().unwrap())),
+        None => {
+            part1(&input);
+            part2(&input);
+        }
+    }
+}
+
+fn total_arrangements(input: &str, fold: usize) -> BigUint {
+    let mut counter = Counter::default();
+    input
+        .lines()
+        .map(|line| counter.count(&Record::from_line(line, fold)))
+        .sum()
 }
 
 fn part1(input: &str) {
-    let mut ans = 0;
-    for line in input.lines() {
-        let mut rec = Record::from_line(line, 1);
-        ans += rec.calc_arrangements();
-    }
-    println!("part1 = {}", ans);
+    println!("part1 = {}", total_arrangements(input, 1));
 }
 
 fn part2(input: &str) {
-    let mut ans = 0;
-    for line in input.lines() {
-        let mut rec = Record::from_line(line, 5);
-        ans += rec.calc_arrangements();
-    }
-    println!("part2 = {}", ans);
+    println!("part2 = {}", total_arrangements(input, 5));
 }
 
 #[derive(Debug)]
 struct Record {
     conditions: Vec<char>,
     groups: Vec<usize>,
-    dp: Vec<Vec<i64>>,
 }
 
 impl Record {
     fn from_line(line: &str, fold: usize) -> Self {
+        assert!(fold >= 1, "fold must be at least 1");
         let parts: Vec<&str> = line.split(' ').collect();
         let mut conditions: Vec<char> = parts[0].chars().collect();
         let groups: Vec<usize> = parts[1].split(',').map(|x| x.parse().unwrap()).collect();
@@ -52,69 +84,252 @@ impl Record {
             .copied()
             .collect();
 
-        let m = conditions.len();
-        let n = groups.len();
-        let dp = vec![vec![-1; n + 1]; m];
+        Self { conditions, groups }
+    }
 
-        Self {
-            conditions,
-            groups,
-            dp,
+    // Every filling of the `?` positions, in lexicographic order (`#` before `.`).
+    fn arrangements(&self) -> Arrangements<'_> {
+        let mut counter = Counter::default();
+        let total = counter.count(self);
+        Arrangements {
+            record: self,
+            counter,
+            next: BigUint::default(),
+            total,
         }
     }
 
-    fn calc_arrangements(&mut self) -> i64 {
-        self.recur(0, 0)
+    fn kth_arrangement(&self, k: BigUint) -> Option<String> {
+        let mut counter = Counter::default();
+        counter.fill(self);
+        counter.kth(self, k)
     }
 
-    fn recur(&mut self, i: usize, j: usize) -> i64 {
-        let m = self.conditions.len();
-        let n = self.groups.len();
-        if i >= m && j >= n {
-            return 1;
+    fn random_arrangement<R: Rng>(&self, rng: &mut R) -> Option<String> {
+        let mut counter = Counter::default();
+        let total = counter.count(self);
+        if total == BigUint::default() {
+            return None;
         }
-        if i >= m {
-            return 0;
-        }
-        if self.dp[i][j] != -1 {
-            return self.dp[i][j];
+        counter.kth(self, rng.gen_biguint_below(&total))
+    }
+}
+
+struct Arrangements<'a> {
+    record: &'a Record,
+    counter: Counter,
+    next: BigUint,
+    total: BigUint,
+}
+
+impl Iterator for Arrangements<'_> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        if self.next >= self.total {
+            return None;
         }
-        if j == n {
-            if self.conditions[i] == '#' {
-                self.dp[i][j] = 0;
-            } else {
-                self.dp[i][j] = self.recur(i + 1, j);
-            }
-            return self.dp[i][j];
+        let ans = self.counter.kth(self.record, self.next.clone());
+        self.next += 1u8;
+        ans
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counter {
+    // `small[i * (n + 1) + j]` counts the fillings of `conditions[i..]` matching `groups[j..]`;
+    // only when that overflows is the record recounted into `big`, whose cells keep
+    // their allocations from one record to the next.
+    small: Vec<u128>,
+    big: Vec<BigUint>,
+    overflowed: bool,
+    dots: Vec<usize>,
+    width: usize,
+}
+
+impl Counter {
+    fn count(&mut self, rec: &Record) -> BigUint {
+        self.fill(rec);
+        self.ways(0, 0)
+    }
+
+    fn ways(&self, i: usize, j: usize) -> BigUint {
+        let idx = i * self.width + j;
+        if self.overflowed {
+            self.big[idx].clone()
+        } else {
+            BigUint::from(self.small[idx])
         }
+    }
 
-        if i + self.groups[j] > m {
-            self.dp[i][j] = 0;
-            return 0;
+    fn fits(&self, rec: &Record, i: usize, j: usize) -> bool {
+        fits(&self.dots, rec, i, j)
+    }
+
+    fn fill(&mut self, rec: &Record) {
+        self.width = rec.groups.len() + 1;
+        self.dots.clear();
+        self.dots.push(0);
+        for &c in &rec.conditions {
+            self.dots
+                .push(self.dots.last().unwrap() + (c == '.') as usize);
         }
-        if self.conditions[i] == '.' {
-            self.dp[i][j] = self.recur(i + 1, j);
-            return self.dp[i][j];
+        self.overflowed = !self.fill_small(rec);
+        if self.overflowed {
+            self.fill_big(rec);
         }
-        let mut ans = 0;
-        if self.conditions[i] == '?' {
-            ans += self.recur(i + 1, j);
+    }
+
+    // false on overflow
+    fn fill_small(&mut self, rec: &Record) -> bool {
+        let m = rec.conditions.len();
+        let n = rec.groups.len();
+        let w = self.width;
+        self.small.clear();
+        self.small.resize((m + 1) * w, 0);
+        self.small[m * w + n] = 1;
+        for i in (0..m).rev() {
+            for j in 0..=n {
+                let mut ans = 0u128;
+                if rec.conditions[i] != '#' {
+                    ans = self.small[(i + 1) * w + j];
+                }
+                if j < n && rec.conditions[i] != '.' && self.fits(rec, i, j) {
+                    let next = (i + rec.groups[j] + 1).min(m);
+                    match ans.checked_add(self.small[next * w + j + 1]) {
+                        Some(sum) => ans = sum,
+                        None => return false,
+                    }
+                }
+                self.small[i * w + j] = ans;
+            }
+        }
+        true
+    }
+
+    fn fill_big(&mut self, rec: &Record) {
+        let m = rec.conditions.len();
+        let n = rec.groups.len();
+        let w = self.width;
+        self.big.resize((m + 1) * w, BigUint::default());
+        for (j, cell) in self.big[m * w..].iter_mut().enumerate() {
+            cell.assign_from_slice(if j == n { &[1] } else { &[] });
         }
-        let mut can_form = true;
-        for k in i + 1..i + self.groups[j] {
-            if self.conditions[k] == '.' {
-                can_form = false;
-                break;
+        for i in (0..m).rev() {
+            // row i is written from the rows after it
+            let (head, tail) = self.big.split_at_mut((i + 1) * w);
+            for j in 0..=n {
+                let cell = &mut head[i * w + j];
+                cell.assign_from_slice(&[]);
+                if rec.conditions[i] != '#' {
+                    *cell += &tail[j];
+                }
+                if j < n && rec.conditions[i] != '.' && fits(&self.dots, rec, i, j) {
+                    let next = (i + rec.groups[j] + 1).min(m);
+                    *cell += &tail[(next - i - 1) * w + j + 1];
+                }
             }
         }
-        if can_form && i + self.groups[j] < m && self.conditions[i + self.groups[j]] == '#' {
-            can_form = false;
+    }
+
+    // The `k`-th filling (from 0) in lexicographic order, with the table already filled
+    // for `rec`. At each `?` the fillings that start a group there come first.
+    fn kth(&self, rec: &Record, mut k: BigUint) -> Option<String> {
+        let m = rec.conditions.len();
+        let n = rec.groups.len();
+        if k >= self.ways(0, 0) {
+            return None;
         }
-        if can_form {
-            ans += self.recur(i + self.groups[j] + 1, j + 1);
+        let mut ans = String::with_capacity(m);
+        let (mut i, mut j) = (0, 0);
+        while i < m {
+            if j < n && rec.conditions[i] != '.' && self.fits(rec, i, j) {
+                let end = i + rec.groups[j];
+                let next = (end + 1).min(m);
+                let cnt = self.ways(next, j + 1);
+                if k < cnt {
+                    ans.extend(std::iter::repeat_n('#', rec.groups[j]));
+                    if end < m {
+                        ans.push('.');
+                    }
+                    i = next;
+                    j += 1;
+                    continue;
+                }
+                k -= cnt;
+            }
+            ans.push('.');
+            i += 1;
         }
+        Some(ans)
+    }
+}
+
+// whether group `j` can start at position `i`, `dots` counting the `.` before each position
+fn fits(dots: &[usize], rec: &Record, i: usize, j: usize) -> bool {
+    let m = rec.conditions.len();
+    let end = i + rec.groups[j];
+    end <= m && dots[end] == dots[i] && (end == m || rec.conditions[end] != '#')
+}
 
-        self.dp[i][j] = ans;
-        self.dp[i][j]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample() {
+        let input = include_str!("../../inputs/test12.txt");
+        assert_eq!(total_arrangements(input, 1), BigUint::from(21u8));
+        assert_eq!(total_arrangements(input, 5), BigUint::from(525152u32));
+        let mut counter = Counter::default();
+        let mut count = |line| counter.count(&Record::from_line(line, 1));
+        assert_eq!(count("?###???????? 3,2,1"), BigUint::from(10u8));
+        assert_eq!(count("??? 1"), BigUint::from(3u8));
+    }
+
+    #[test]
+    fn test_big_table() {
+        let mut counter = Counter::default();
+        let rec = Record::from_line("?###???????? 3,2,1", 5);
+        let small = counter.count(&rec);
+        counter.fill_big(&rec);
+        counter.overflowed = true;
+        assert_eq!(counter.ways(0, 0), small);
+        // a larger record reuses the cells, then overflows u128 on its own
+        let rec = Record::from_line("?###???????? 3,2,1", 40);
+        assert!(counter.count(&rec) > BigUint::from(u128::MAX));
+        assert!(counter.overflowed);
+        assert_eq!(
+            counter.count(&Record::from_line("??? 1", 1)),
+            BigUint::from(3u8)
+        );
+    }
+
+    #[test]
+    fn test_fillings() {
+        let record = Record::from_line("?###???????? 3,2,1", 1);
+        let all: Vec<String> = record.arrangements().collect();
+        assert_eq!(all.len(), 10);
+        assert_eq!(all[0], ".###.##.#...");
+        assert!(all.windows(2).all(|w| w[0] < w[1]));
+        let mut counter = Counter::default();
+        for filling in &all {
+            let line = format!("{} 3,2,1", filling);
+            assert_eq!(
+                counter.count(&Record::from_line(&line, 1)),
+                BigUint::from(1u8)
+            );
+        }
+        assert_eq!(
+            record.kth_arrangement(BigUint::from(9u8)).as_ref(),
+            all.last()
+        );
+        assert_eq!(record.kth_arrangement(BigUint::from(10u8)), None);
+        let mut rng = rand::thread_rng();
+        let sample = record.random_arrangement(&mut rng).unwrap();
+        assert!(all.contains(&sample));
+        let none = Record::from_line("#.# 3", 1);
+        assert_eq!(none.random_arrangement(&mut rng), None);
+        assert_eq!(none.arrangements().count(), 0);
     }
 }
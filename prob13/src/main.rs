@@ -1,72 +1,269 @@
+use std::env;
+use std::fmt;
 use std::fs::read_to_string;
 
 fn main() {
     let input = read_to_string("inputs/input13.txt").unwrap();
-    part1(&input, 0);
-    part1(&input, 1);
+    let patterns = parse_patterns(&input);
+    // [report [allowed_diff]]
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(|s| s.as_str()) == Some("report") {
+        let allowed_diff = args.get(1).map_or(0, |d| d.parse().unwrap());
+        for (i, pattern) in patterns.iter().enumerate() {
+            match pattern.find(allowed_diff) {
+                Ok(found) => {
+                    for r in found {
+                        println!("pattern {}: {}", i, r);
+                    }
+                }
+                Err(e) => println!("pattern {}: {}", i, e),
+            }
+        }
+        return;
+    }
+    for (part, allowed_diff) in [(1, 0), (2, 1)] {
+        match total_score(&patterns, allowed_diff) {
+            Ok(score) => println!("part{} = {}", part, score),
+            Err((i, e)) => println!("part{}: pattern {}: {}", part, i, e),
+        }
+    }
 }
 
-fn transpose<T>(v: &Vec<Vec<T>>) -> Vec<Vec<T>>
-where
-    T: Clone,
-{
-    assert!(!v.is_empty());
-    (0..v[0].len())
-        .map(|i| v.iter().map(|inner| inner[i].clone()).collect::<Vec<T>>())
+fn parse_patterns(input: &str) -> Vec<Pattern> {
+    input
+        .split("\n\n")
+        .filter(|block| !block.trim().is_empty())
+        .map(Pattern::from_block)
         .collect()
 }
 
-fn part1(input: &str, allowed_diff: usize) {
-    let mut cur = vec![];
+fn total_score(patterns: &[Pattern], allowed_diff: usize) -> Result<usize, (usize, MirrorError)> {
     let mut ans = 0;
-    for line in input.lines() {
-        if line.is_empty() {
-            ans += calc_score(&cur, allowed_diff);
-            cur = vec![];
-        } else {
-            let row: Vec<char> = line.chars().collect();
-            cur.push(row);
+    for (i, p) in patterns.iter().enumerate() {
+        let found = p.find(allowed_diff).map_err(|e| (i, e))?;
+        ans += found.iter().map(|r| r.score()).sum::<usize>();
+    }
+    Ok(ans)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Reflection {
+    axis: Axis,
+    index: usize,
+    diff: usize,
+    // (row, col) of each mismatched cell on the top or left side of the line;
+    // flipping it or its mirror image makes the reflection exact.
+    smudges: Vec<(usize, usize)>,
+}
+
+impl Reflection {
+    fn score(&self) -> usize {
+        match self.axis {
+            Axis::Horizontal => self.index * 100,
+            Axis::Vertical => self.index,
         }
     }
-    ans += calc_score(&cur, allowed_diff);
-    println!("part1 = {}", ans);
 }
 
-fn calc_score(matrix: &Vec<Vec<char>>, allowed_diff: usize) -> usize {
-    let mut ans = 0;
-    let row_mirror = find_mirror_in_row(matrix, allowed_diff);
-    ans += row_mirror * 100;
-    let trans = transpose(matrix);
-    ans += find_mirror_in_row(&trans, allowed_diff);
-    ans
+impl fmt::Display for Reflection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} {} diff {}", self.axis, self.index, self.diff)?;
+        if !self.smudges.is_empty() {
+            write!(f, " smudges {:?}", self.smudges)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum MirrorError {
+    NoReflection { allowed_diff: usize },
 }
 
-fn find_mirror_in_row(matrix: &Vec<Vec<char>>, allowed_diff: usize) -> usize {
-    for i in 1..matrix.len() as i32 {
-        let mut up = i - 1;
-        let mut down = i;
-        let mut diff = 0;
-        while up >= 0 && (down as usize) < matrix.len() {
-            diff += diff_count(&matrix[up as usize], &matrix[down as usize]);
-            if diff > allowed_diff {
-                break;
+impl fmt::Display for MirrorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NoReflection { allowed_diff } => {
+                write!(f, "no reflection with exactly {} differences", allowed_diff)
             }
-            up -= 1;
-            down += 1;
         }
-        if (up < 0 || (down as usize) >= matrix.len()) && diff == allowed_diff {
-            return i as usize;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Mask {
+    words: Vec<u64>,
+}
+
+impl Mask {
+    fn new(len: usize) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(64)],
         }
     }
-    0
+
+    fn set(&mut self, i: usize) {
+        self.words[i / 64] |= 1 << (i % 64);
+    }
+
+    fn diff_count(&self, other: &Mask) -> usize {
+        self.words
+            .iter()
+            .zip(&other.words)
+            .map(|(a, b)| (a ^ b).count_ones() as usize)
+            .sum()
+    }
+
+    fn diff_positions(&self, other: &Mask) -> Vec<usize> {
+        let mut ans = vec![];
+        for (w, (a, b)) in self.words.iter().zip(&other.words).enumerate() {
+            let mut x = a ^ b;
+            while x != 0 {
+                ans.push(w * 64 + x.trailing_zeros() as usize);
+                x &= x - 1;
+            }
+        }
+        ans
+    }
 }
 
-fn diff_count(r1: &[char], r2: &[char]) -> usize {
-    let mut ans = 0;
-    for i in 0..r1.len() {
-        if r1[i] != r2[i] {
-            ans += 1;
+#[derive(Debug)]
+struct Pattern {
+    rows: Vec<Mask>,
+    cols: Vec<Mask>,
+}
+
+impl Pattern {
+    fn from_block(block: &str) -> Self {
+        let lines: Vec<&[u8]> = block.lines().map(|l| l.as_bytes()).collect();
+        let width = lines[0].len();
+        let mut rows = vec![Mask::new(width); lines.len()];
+        let mut cols = vec![Mask::new(lines.len()); width];
+        for (r, line) in lines.iter().enumerate() {
+            assert_eq!(line.len(), width, "ragged pattern");
+            for (c, &b) in line.iter().enumerate() {
+                if b == b'#' {
+                    rows[r].set(c);
+                    cols[c].set(r);
+                }
+            }
+        }
+        Self { rows, cols }
+    }
+
+    // smudges are left empty here and only filled in by `find`
+    fn reflections(&self) -> Vec<Reflection> {
+        let mut ans = reflect_lines(&self.rows, Axis::Horizontal);
+        ans.extend(reflect_lines(&self.cols, Axis::Vertical));
+        ans
+    }
+
+    fn find(&self, allowed_diff: usize) -> Result<Vec<Reflection>, MirrorError> {
+        let found: Vec<Reflection> = self
+            .reflections()
+            .into_iter()
+            .filter(|r| r.diff == allowed_diff)
+            .map(|mut r| {
+                r.smudges = self.smudges(&r);
+                r
+            })
+            .collect();
+        if found.is_empty() {
+            return Err(MirrorError::NoReflection { allowed_diff });
         }
+        Ok(found)
+    }
+
+    fn smudges(&self, r: &Reflection) -> Vec<(usize, usize)> {
+        let lines = match r.axis {
+            Axis::Horizontal => &self.rows,
+            Axis::Vertical => &self.cols,
+        };
+        let mut ans = vec![];
+        for (a, b) in (0..r.index).rev().zip(r.index..lines.len()) {
+            for pos in lines[a].diff_positions(&lines[b]) {
+                ans.push(match r.axis {
+                    Axis::Horizontal => (a, pos),
+                    Axis::Vertical => (pos, a),
+                });
+            }
+        }
+        ans.sort();
+        ans
+    }
+}
+
+fn reflect_lines(lines: &[Mask], axis: Axis) -> Vec<Reflection> {
+    let mut ans = vec![];
+    for i in 1..lines.len() {
+        let diff = (0..i)
+            .rev()
+            .zip(i..lines.len())
+            .map(|(a, b)| lines[a].diff_count(&lines[b]))
+            .sum();
+        ans.push(Reflection {
+            axis,
+            index: i,
+            diff,
+            smudges: vec![],
+        });
     }
     ans
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reflections() {
+        let patterns = parse_patterns(include_str!("../../inputs/test13.txt"));
+        assert_eq!(total_score(&patterns, 0), Ok(405));
+        assert_eq!(total_score(&patterns, 1), Ok(400));
+        let found = patterns[0].find(1).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].axis, Axis::Horizontal);
+        assert_eq!(found[0].index, 3);
+        assert_eq!(found[0].smudges, vec![(0, 0)]);
+        assert_eq!(patterns[1].find(1).unwrap()[0].smudges, vec![(0, 4)]);
+        assert_eq!(patterns[0].reflections().len(), 6 + 8);
+        let none = Pattern::from_block("#.\n..");
+        assert_eq!(
+            none.find(0),
+            Err(MirrorError::NoReflection { allowed_diff: 0 })
+        );
+        let mut patterns = patterns;
+        patterns.push(none);
+        assert_eq!(
+            total_score(&patterns, 0),
+            Err((2, MirrorError::NoReflection { allowed_diff: 0 }))
+        );
+    }
+
+    #[test]
+    fn test_wide() {
+        // 130 columns mirrored about the middle, one smudge past the first word
+        let half: String = (0..65)
+            .map(|i| if i % 3 == 0 { '#' } else { '.' })
+            .collect();
+        let mirrored: String = half.chars().rev().collect();
+        let row = format!("{}{}", half, mirrored);
+        let mut smudged = row.clone().into_bytes();
+        smudged[100] = if smudged[100] == b'#' { b'.' } else { b'#' };
+        let block = format!("{}\n{}", row, String::from_utf8(smudged).unwrap());
+        let pattern = Pattern::from_block(&block);
+        assert_eq!(pattern.rows[0].words.len(), 3);
+        let exact = pattern.find(0).unwrap_or_default();
+        assert!(exact.iter().all(|r| r.score() != 65));
+        let found = pattern.find(1).unwrap();
+        let at = |axis, index| found.iter().find(|r| r.axis == axis && r.index == index);
+        assert_eq!(at(Axis::Horizontal, 1).unwrap().smudges, vec![(0, 100)]);
+        assert_eq!(at(Axis::Vertical, 65).unwrap().smudges, vec![(1, 29)]);
+    }
+}
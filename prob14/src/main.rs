@@ -1,22 +1,56 @@
 use std::{
     collections::HashMap,
+    env,
     fmt::{Debug, Write},
     fs::read_to_string,
 };
 
 fn main() {
     let input = read_to_string("inputs/input14.txt").unwrap();
+    // [sequence [times]], e.g. `NNEW 1000`; part 2 spins NWSE 1000000000 times
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Some(seq) = args.first() {
+        let seq = Direction::parse_sequence(seq);
+        let times = args.get(1).map_or(1, |n| n.parse().unwrap());
+        let mut board = Board::from_input(&input);
+        println!("{}", board.cycle(&seq, times));
+        return;
+    }
     let mut board = Board::from_input(&input);
-    board.tilt_north();
+    board.tilt(Direction::North);
     println!("part1 = {}", board.calc_load());
 
     let mut board = Board::from_input(&input);
-    let part2 = board.cycle(1000000000);
+    let part2 = board.cycle(&Direction::parse_sequence("NWSE"), 1000000000);
     println!("part2 = {:?}", part2);
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    North,
+    West,
+    South,
+    East,
+}
+
+impl Direction {
+    fn parse_sequence(s: &str) -> Vec<Self> {
+        s.chars()
+            .map(|c| match c {
+                'N' => Self::North,
+                'W' => Self::West,
+                'S' => Self::South,
+                'E' => Self::East,
+                _ => panic!("invalid direction {c}"),
+            })
+            .collect()
+    }
+}
+
 struct Board {
     matrix: Vec<Vec<char>>,
+    col_segments: Vec<Vec<(usize, usize)>>,
+    row_segments: Vec<Vec<(usize, usize)>>,
 }
 
 impl Debug for Board {
@@ -37,138 +71,126 @@ impl Board {
             let row: Vec<char> = line.chars().collect();
             matrix.push(row);
         }
-        Self { matrix }
+        let m = matrix.len();
+        let n = matrix[0].len();
+        let col_segments = segments(&matrix, (0..n).map(|j| (0..m).map(move |i| (i, j))));
+        let row_segments = segments(&matrix, (0..m).map(|i| (0..n).map(move |j| (i, j))));
+        Self {
+            matrix,
+            col_segments,
+            row_segments,
+        }
     }
 
-    fn tilt_north(&mut self) {
-        let m = self.matrix.len();
-        let n = self.matrix[0].len();
-        for i in 0..m {
-            for j in 0..n {
-                if self.matrix[i][j] == 'O' {
-                    let mut k = i as i32 - 1;
-                    while k >= 0 {
-                        if self.matrix[k as usize][j] == '.' {
-                            k -= 1;
-                        } else {
-                            break;
-                        }
-                    }
-                    self.matrix[i][j] = '.';
-                    self.matrix[(k + 1) as usize][j] = 'O';
-                }
+    // Rolls every round rock as far as it goes: each segment gets its rocks
+    // packed at the end facing `dir`, so a tilt is linear in the cells.
+    fn tilt(&mut self, dir: Direction) {
+        let segments = match dir {
+            Direction::North | Direction::South => &self.col_segments,
+            Direction::West | Direction::East => &self.row_segments,
+        };
+        let towards_start = matches!(dir, Direction::North | Direction::West);
+        for seg in segments {
+            let rocks = seg
+                .iter()
+                .filter(|&&(i, j)| self.matrix[i][j] == 'O')
+                .count();
+            for (k, &(i, j)) in seg.iter().enumerate() {
+                let filled = if towards_start {
+                    k < rocks
+                } else {
+                    k >= seg.len() - rocks
+                };
+                self.matrix[i][j] = if filled { 'O' } else { '.' };
             }
         }
     }
 
-    fn tilt_west(&mut self) {
+    fn calc_load(&self) -> usize {
+        let mut ans = 0;
         let m = self.matrix.len();
         let n = self.matrix[0].len();
         for i in 0..m {
             for j in 0..n {
                 if self.matrix[i][j] == 'O' {
-                    let mut k = j as i32 - 1;
-                    while k >= 0 {
-                        if self.matrix[i][k as usize] == '.' {
-                            k -= 1;
-                        } else {
-                            break;
-                        }
-                    }
-                    self.matrix[i][j] = '.';
-                    self.matrix[i][(k + 1) as usize] = 'O';
+                    ans += m - i;
                 }
             }
         }
+        ans
     }
 
-    fn tilt_south(&mut self) {
-        let m = self.matrix.len();
-        let n = self.matrix[0].len();
-        for i in (0..m).rev() {
-            for j in 0..n {
-                if self.matrix[i][j] == 'O' {
-                    let mut k = i + 1;
-                    while k < m {
-                        if self.matrix[k][j] == '.' {
-                            k += 1;
-                        } else {
-                            break;
-                        }
-                    }
-                    self.matrix[i][j] = '.';
-                    self.matrix[k - 1][j] = 'O';
-                }
+    // leaves the board as it is after all `n` rounds, jumping ahead once a state repeats
+    fn cycle(&mut self, seq: &[Direction], n: usize) -> usize {
+        let mut states = vec![self.matrix.clone()];
+        let mut seen = HashMap::new();
+        seen.insert(self.matrix.clone(), 0);
+        for i in 1..=n {
+            for &dir in seq {
+                self.tilt(dir);
             }
-        }
-    }
-
-    fn tilt_east(&mut self) {
-        let m = self.matrix.len();
-        let n = self.matrix[0].len();
-        for i in 0..m {
-            for j in (0..n).rev() {
-                if self.matrix[i][j] == 'O' {
-                    let mut k = j + 1;
-                    while k < n {
-                        if self.matrix[i][k] == '.' {
-                            k += 1;
-                        } else {
-                            break;
-                        }
-                    }
-                    self.matrix[i][j] = '.';
-                    self.matrix[i][k - 1] = 'O';
-                }
+            if let Some(&start) = seen.get(&self.matrix) {
+                let pattern_len = i - start;
+                self.matrix = states[start + (n - start) % pattern_len].clone();
+                break;
             }
+            seen.insert(self.matrix.clone(), i);
+            states.push(self.matrix.clone());
         }
+        self.calc_load()
     }
+}
 
-    fn calc_load(&self) -> usize {
-        let mut ans = 0;
-        let m = self.matrix.len();
-        let n = self.matrix[0].len();
-        for i in 0..m {
-            for j in 0..n {
-                if self.matrix[i][j] == 'O' {
-                    ans += m - i;
+fn segments<L, I>(matrix: &[Vec<char>], lines: L) -> Vec<Vec<(usize, usize)>>
+where
+    L: Iterator<Item = I>,
+    I: Iterator<Item = (usize, usize)>,
+{
+    let mut ans = vec![];
+    for line in lines {
+        let mut cur = vec![];
+        for (i, j) in line {
+            if matrix[i][j] == '#' {
+                if !cur.is_empty() {
+                    ans.push(std::mem::take(&mut cur));
                 }
+            } else {
+                cur.push((i, j));
             }
         }
-        ans
+        if !cur.is_empty() {
+            ans.push(cur);
+        }
     }
+    ans
+}
 
-    fn cycle(&mut self, n: usize) -> usize {
-        let mut scores = vec![];
-        let mut patterns = HashMap::new();
-        scores.push(self.calc_load());
-        for i in 0..n {
-            self.tilt_north();
-            self.tilt_west();
-            self.tilt_south();
-            self.tilt_east();
-            scores.push(self.calc_load());
-            if i >= 7 {
-                let pattern = (
-                    scores[i - 6],
-                    scores[i - 5],
-                    scores[i - 4],
-                    scores[i - 3],
-                    scores[i - 2],
-                    scores[i - 1],
-                    scores[i],
-                );
-                if let std::collections::hash_map::Entry::Vacant(e) = patterns.entry(pattern) {
-                    e.insert(i - 6);
-                } else {
-                    let start = patterns.get(&pattern).unwrap();
-                    let pattern_len = i - 6 - start;
-                    println!("{} {}", start, pattern_len);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-                    return scores[start + (n - start) % pattern_len];
+    #[test]
+    fn test_sample() {
+        let input = include_str!("../../inputs/test14.txt");
+        let mut board = Board::from_input(input);
+        board.tilt(Direction::North);
+        assert_eq!(board.calc_load(), 136);
+        let mut board = Board::from_input(input);
+        assert_eq!(
+            board.cycle(&Direction::parse_sequence("NWSE"), 1000000000),
+            64
+        );
+        for (seq, n) in [("NWSE", 20), ("NNEW", 7), ("SE", 1)] {
+            let mut fast = Board::from_input(input);
+            let load = fast.cycle(&Direction::parse_sequence(seq), n);
+            let mut slow = Board::from_input(input);
+            for _ in 0..n {
+                for dir in Direction::parse_sequence(seq) {
+                    slow.tilt(dir);
                 }
             }
+            assert_eq!(fast.matrix, slow.matrix, "{} x {}", seq, n);
+            assert_eq!(load, slow.calc_load());
         }
-        0
     }
 }
@@ -1,5 +1,6 @@
 use std::fmt;
 use std::fs::read_to_string;
+use utils::polygon::Polygon;
 
 fn main() {
     let input = read_to_string("inputs/input18.txt").unwrap();
@@ -18,17 +19,14 @@ fn part2(input: &str) {
         instructions.push(parse_line2(line));
     }
 
-    let mut area = 0;
-    let mut preimeter = 0;
+    let mut vertices = vec![];
     let mut p = Point::new(0, 0);
-    // showlace formula
     for (dir, steps) in instructions {
-        let np = p.dig(dir, steps);
-        area += p.x * np.y - np.x * p.y;
-        preimeter += steps;
-        p = np;
+        vertices.push((p.x, p.y));
+        p = p.dig(dir, steps);
     }
-    let ans = (area.abs() + preimeter) / 2 + 1;
+    let lagoon = Polygon::new(vertices);
+    let ans = lagoon.interior_points() + lagoon.boundary_points();
     println!("part2 = {}", ans);
 }
 
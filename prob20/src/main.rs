@@ -1,11 +1,19 @@
 use std::{
     collections::{HashMap, VecDeque},
+    env,
     fs::read_to_string,
 };
+use utils::dot::Digraph;
 
 fn main() {
     let input = read_to_string("inputs/input20.txt").unwrap();
     let mut machine = Machine::from_input(&input);
+    // dot [highlight-start]
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(|s| s.as_str()) == Some("dot") {
+        print!("{}", machine.to_dot(args.get(1).map(|s| s.as_str())));
+        return;
+    }
     machine.part1();
     part2(&input);
 }
@@ -218,6 +226,41 @@ impl<'a> Machine<'a> {
         false
     }
 
+    fn to_dot(&self, highlight: Option<&str>) -> String {
+        let mut g = Digraph::new("machines");
+        let mut names: Vec<&str> = self.modules.keys().copied().collect();
+        names.sort();
+        for name in names.iter() {
+            let (shape, label) = match self.modules[name].module_type {
+                ModuleType::FlipFlop(_) => ("box", format!("%{}", name)),
+                ModuleType::Conjunction(_) => ("invhouse", format!("&{}", name)),
+                ModuleType::BroadCaster => ("doublecircle", name.to_string()),
+            };
+            g.node(name, &[("shape", shape), ("label", &label)]);
+        }
+        // modules only ever sent to, such as rx
+        let mut sinks: Vec<&str> = self
+            .modules
+            .values()
+            .flat_map(|m| m.downstream.iter().copied())
+            .filter(|ds| !self.modules.contains_key(ds))
+            .collect();
+        sinks.sort();
+        sinks.dedup();
+        for sink in sinks {
+            g.node(sink, &[("shape", "plaintext")]);
+        }
+        for name in names {
+            for ds in self.modules[name].downstream.iter() {
+                g.edge(name, ds, &[]);
+            }
+        }
+        if let Some(start) = highlight {
+            g.highlight_reachable(start);
+        }
+        g.to_dot()
+    }
+
     fn first_high(&mut self, name: &str) -> usize {
         let mut cnt = 0;
         loop {
@@ -245,3 +288,24 @@ fn gcd_of_two_numbers(a: usize, b: usize) -> usize {
     }
     gcd_of_two_numbers(b, a % b)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dot() {
+        let machine = Machine::from_input(include_str!("../../inputs/test20_2.txt"));
+        let dot = machine.to_dot(None);
+        assert!(dot.contains("\t\"broadcaster\" [shape=\"doublecircle\", label=\"broadcaster\"]\n"));
+        assert!(dot.contains("\t\"a\" [shape=\"box\", label=\"%a\"]\n"));
+        assert!(dot.contains("\t\"con\" [shape=\"invhouse\", label=\"&con\"]\n"));
+        assert!(dot.contains("\t\"output\" [shape=\"plaintext\"]\n"));
+        assert!(dot.contains("\t\"con\" -> \"output\"\n"));
+        assert_eq!(dot.matches(" -> ").count(), 6);
+        let dot = machine.to_dot(Some("b"));
+        assert!(dot.contains("\t\"b\" -> \"con\" [color=\"red\"]\n"));
+        assert!(dot.contains("\t\"output\" [shape=\"plaintext\", color=\"red\"]\n"));
+        assert!(dot.contains("\t\"a\" -> \"inv\" [color=\"gray\"]\n"));
+    }
+}
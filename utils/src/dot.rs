@@ -0,0 +1,122 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Write;
+
+type Attrs = Vec<(String, String)>;
+
+#[derive(Debug, Default)]
+pub struct Digraph {
+    name: String,
+    nodes: Vec<(String, Attrs)>,
+    edges: Vec<(String, String, Attrs)>,
+    highlight: Option<String>,
+}
+
+impl Digraph {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn node(&mut self, id: &str, attrs: &[(&str, &str)]) {
+        self.nodes.push((id.to_string(), to_owned(attrs)));
+    }
+
+    pub fn edge(&mut self, from: &str, to: &str, attrs: &[(&str, &str)]) {
+        self.edges
+            .push((from.to_string(), to.to_string(), to_owned(attrs)));
+    }
+
+    // Draws everything reachable from `start` in red and greys out the rest.
+    pub fn highlight_reachable(&mut self, start: &str) {
+        self.highlight = Some(start.to_string());
+    }
+
+    pub fn reachable<'a>(&'a self, start: &'a str) -> HashSet<&'a str> {
+        let mut adj: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (from, to, _) in &self.edges {
+            adj.entry(from).or_default().push(to);
+        }
+        let mut seen = HashSet::from([start]);
+        let mut q = VecDeque::from([start]);
+        while let Some(cur) = q.pop_front() {
+            for &next in adj.get(cur).into_iter().flatten() {
+                if seen.insert(next) {
+                    q.push_back(next);
+                }
+            }
+        }
+        seen
+    }
+
+    pub fn to_dot(&self) -> String {
+        let reachable = self.highlight.as_deref().map(|s| self.reachable(s));
+        let color = |ids: &[&str]| match &reachable {
+            Some(r) if ids.iter().all(|id| r.contains(id)) => Some(("color", "red")),
+            Some(_) => Some(("color", "gray")),
+            None => None,
+        };
+        let mut ans = String::new();
+        writeln!(ans, "digraph {} {{", quote(&self.name)).unwrap();
+        for (id, attrs) in &self.nodes {
+            let extra = color(&[id]);
+            writeln!(ans, "\t{}{}", quote(id), render_attrs(attrs, extra)).unwrap();
+        }
+        for (from, to, attrs) in &self.edges {
+            let extra = color(&[from, to]);
+            let attrs = render_attrs(attrs, extra);
+            writeln!(ans, "\t{} -> {}{}", quote(from), quote(to), attrs).unwrap();
+        }
+        ans.push_str("}\n");
+        ans
+    }
+}
+
+fn to_owned(attrs: &[(&str, &str)]) -> Attrs {
+    attrs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+fn quote(id: &str) -> String {
+    format!("\"{}\"", id.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn render_attrs(attrs: &[(String, String)], extra: Option<(&str, &str)>) -> String {
+    let mut parts: Vec<String> = attrs
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, quote(v)))
+        .collect();
+    if let Some((k, v)) = extra {
+        parts.push(format!("{}={}", k, quote(v)));
+    }
+    if parts.is_empty() {
+        String::new()
+    } else {
+        format!(" [{}]", parts.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render() {
+        let mut g = Digraph::new("g");
+        g.node("a", &[("shape", "box")]);
+        g.node("b", &[]);
+        g.node("c", &[]);
+        g.edge("a", "b", &[("label", "L")]);
+        g.edge("c", "a", &[]);
+        assert_eq!(
+            g.to_dot(),
+            "digraph \"g\" {\n\t\"a\" [shape=\"box\"]\n\t\"b\"\n\t\"c\"\n\t\"a\" -> \"b\" [label=\"L\"]\n\t\"c\" -> \"a\"\n}\n"
+        );
+        g.highlight_reachable("a");
+        assert_eq!(g.reachable("a"), HashSet::from(["a", "b"]));
+        assert!(g.to_dot().contains("\"c\" -> \"a\" [color=\"gray\"]"));
+    }
+}
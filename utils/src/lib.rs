@@ -1,3 +1,6 @@
+pub mod dot;
+pub mod polygon;
+
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Ord, Eq, Hash)]
 pub struct Point {
     pub x: usize,
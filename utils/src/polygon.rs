@@ -0,0 +1,65 @@
+#[derive(Debug, Clone)]
+pub struct Polygon {
+    vertices: Vec<(i64, i64)>,
+}
+
+impl Polygon {
+    pub fn new(vertices: Vec<(i64, i64)>) -> Self {
+        Self { vertices }
+    }
+
+    fn edges(&self) -> impl Iterator<Item = ((i64, i64), (i64, i64))> + '_ {
+        let n = self.vertices.len();
+        (0..n).map(move |i| (self.vertices[i], self.vertices[(i + 1) % n]))
+    }
+
+    // Twice the signed area by the shoelace formula, positive when the vertices
+    // run counter-clockwise with x to the right and y up.
+    pub fn doubled_signed_area(&self) -> i64 {
+        self.edges()
+            .map(|((x1, y1), (x2, y2))| x1 * y2 - x2 * y1)
+            .sum()
+    }
+
+    pub fn boundary_points(&self) -> i64 {
+        self.edges()
+            .map(|((x1, y1), (x2, y2))| gcd((x2 - x1).abs(), (y2 - y1).abs()))
+            .sum()
+    }
+
+    // Lattice points strictly inside, by Pick's theorem: A = I + B / 2 - 1.
+    pub fn interior_points(&self) -> i64 {
+        (self.doubled_signed_area().abs() - self.boundary_points()) / 2 + 1
+    }
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        return a;
+    }
+    gcd(b, a % b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn square() {
+        let p = Polygon::new(vec![(0, 0), (4, 0), (4, 4), (0, 4)]);
+        assert_eq!(p.doubled_signed_area(), 32);
+        assert_eq!(p.boundary_points(), 16);
+        assert_eq!(p.interior_points(), 9);
+        let p = Polygon::new(vec![(0, 4), (4, 4), (4, 0), (0, 0)]);
+        assert_eq!(p.doubled_signed_area(), -32);
+        assert_eq!(p.interior_points(), 9);
+    }
+
+    #[test]
+    fn triangle() {
+        let p = Polygon::new(vec![(0, 0), (3, 0), (0, 3)]);
+        assert_eq!(p.doubled_signed_area(), 9);
+        assert_eq!(p.boundary_points(), 9);
+        assert_eq!(p.interior_points(), 1);
+    }
+}